@@ -0,0 +1,60 @@
+/// A source of `(input, target)` examples addressed by index.
+pub trait Dataset {
+    fn len(&self) -> usize;
+
+    /// The `i`th example. Panics if `i >= self.len()`.
+    fn get(&self, i: usize) -> (Vec<f64>, Vec<f64>);
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A dataset held entirely in memory as parallel lists of inputs and
+/// targets.
+pub struct InMemoryDataset {
+    inputs: Vec<Vec<f64>>,
+    targets: Vec<Vec<f64>>,
+}
+
+impl InMemoryDataset {
+    pub fn new(inputs: Vec<Vec<f64>>, targets: Vec<Vec<f64>>) -> Self {
+        assert_eq!(
+            inputs.len(),
+            targets.len(),
+            "{} inputs but {} targets",
+            inputs.len(),
+            targets.len()
+        );
+        InMemoryDataset { inputs, targets }
+    }
+}
+
+impl Dataset for InMemoryDataset {
+    fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    fn get(&self, i: usize) -> (Vec<f64>, Vec<f64>) {
+        (self.inputs[i].clone(), self.targets[i].clone())
+    }
+}
+
+#[test]
+fn test_in_memory_dataset() {
+    let inputs = vec![vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+    let targets = vec![vec![1.0], vec![1.0], vec![0.0]];
+    let dataset = InMemoryDataset::new(inputs.clone(), targets.clone());
+    assert_eq!(dataset.len(), 3);
+    assert!(!dataset.is_empty());
+    let items: Vec<(Vec<f64>, Vec<f64>)> = (0..dataset.len()).map(|i| dataset.get(i)).collect();
+    let expected: Vec<(Vec<f64>, Vec<f64>)> = inputs.into_iter().zip(targets).collect();
+    assert_eq!(items, expected);
+    assert!(InMemoryDataset::new(vec![], vec![]).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "1 inputs but 0 targets")]
+fn test_in_memory_dataset_mismatch() {
+    InMemoryDataset::new(vec![vec![0.0]], vec![]);
+}
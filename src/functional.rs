@@ -0,0 +1,88 @@
+use crate::Value;
+
+/// Softmax over `logits`. The largest logit is subtracted before
+/// exponentiating so large inputs don't overflow; since softmax is invariant
+/// to that shift it is treated as a constant.
+pub fn softmax(logits: &[Value]) -> Vec<Value> {
+    let max = logits
+        .iter()
+        .map(|logit| logit.data())
+        .fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<Value> = logits.iter().map(|logit| (logit - max).exp()).collect();
+    let total: Value = exps.iter().sum();
+    exps.iter().map(|e| e / &total).collect()
+}
+
+/// Log-softmax over `logits`, `x_i - max - log(sum(exp(x_j - max)))`.
+/// Unlike `log(softmax(x))` this never takes the log of an underflowed
+/// probability.
+pub fn log_softmax(logits: &[Value]) -> Vec<Value> {
+    let max = logits
+        .iter()
+        .map(|logit| logit.data())
+        .fold(f64::NEG_INFINITY, f64::max);
+    let shifted: Vec<Value> = logits.iter().map(|logit| logit - max).collect();
+    let total: Value = shifted.iter().map(|s| s.exp()).sum();
+    let log_total = total.log();
+    shifted.iter().map(|s| s - &log_total).collect()
+}
+
+/// One-hot encoding of `class` as `n_classes` fresh leaves, for use as a
+/// classification target. Panics if `class >= n_classes`.
+pub fn one_hot(class: usize, n_classes: usize) -> Vec<Value> {
+    assert!(
+        class < n_classes,
+        "class {} out of range for {} classes",
+        class,
+        n_classes
+    );
+    (0..n_classes)
+        .map(|i| Value::new(if i == class { 1.0 } else { 0.0 }))
+        .collect()
+}
+
+#[test]
+fn test_softmax() {
+    let logits = vec![Value::new(1.0), Value::new(2.0), Value::new(3.0)];
+    let probs = softmax(&logits);
+    let total: f64 = probs.iter().map(|p| p.data()).sum();
+    assert!((total - 1.0).abs() < 1e-12);
+
+    // cross-entropy against class 2: d/dlogits = softmax - onehot
+    let mut loss = -&probs[2].log();
+    loss.backward();
+    let onehot = [0.0, 0.0, 1.0];
+    for i in 0..3 {
+        let expected = probs[i].data() - onehot[i];
+        assert!(logits[i].grad_approx_eq(expected, 1e-12));
+    }
+
+    let large = softmax(&[Value::new(1000.0), Value::new(1000.0)]);
+    assert!(large[0].approx_eq(0.5, 1e-12));
+}
+
+#[test]
+fn test_one_hot() {
+    let target = one_hot(1, 3);
+    assert_eq!(crate::values_to_f64(&target), vec![0.0, 1.0, 0.0]);
+}
+
+#[test]
+#[should_panic(expected = "class 3 out of range for 3 classes")]
+fn test_one_hot_out_of_range() {
+    one_hot(3, 3);
+}
+
+#[test]
+fn test_log_softmax() {
+    let logits = Value::vec_from(&[0.5, -1.0, 2.0]);
+    let log_probs = log_softmax(&logits);
+    let probs = softmax(&logits);
+    for (lp, p) in log_probs.iter().zip(probs.iter()) {
+        assert!(lp.approx_eq(p.data().ln(), 1e-12));
+    }
+
+    // stays finite where softmax underflows to zero
+    let extreme = log_softmax(&Value::vec_from(&[0.0, 1000.0]));
+    assert!(extreme[0].approx_eq(-1000.0, 1e-9));
+}
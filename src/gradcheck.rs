@@ -0,0 +1,42 @@
+use crate::Value;
+
+/// Compares the gradients backward computes for `f` at `inputs` against
+/// central finite differences, perturbing each input's data by `±epsilon`.
+///
+/// Returns the largest error over all inputs, relative to the magnitude of
+/// the gradient (or absolute, for gradients smaller than 1). Afterwards every
+/// input holds its analytic gradient and its original data.
+pub fn grad_check(f: impl Fn(&[Value]) -> Value, inputs: &[Value], epsilon: f64) -> f64 {
+    let mut output = f(inputs);
+    output.zero_grad_graph();
+    output.backward();
+
+    let mut max_error: f64 = 0.0;
+    for input in inputs {
+        let mut input = input.clone();
+        let data = input.data();
+        input.set_data(data + epsilon);
+        let plus = f(inputs).data();
+        input.set_data(data - epsilon);
+        let minus = f(inputs).data();
+        input.set_data(data);
+
+        let numeric = (plus - minus) / (2.0 * epsilon);
+        let analytic = input.grad_f64();
+        let scale = analytic.abs().max(numeric.abs()).max(1.0);
+        max_error = max_error.max((analytic - numeric).abs() / scale);
+    }
+    max_error
+}
+
+#[test]
+fn test_grad_check() {
+    let inputs = Value::vec_from(&[0.5, -1.2, 2.0]);
+    let f = |x: &[Value]| &(&x[0] * &x[1]).tanh() + &(&x[2].pow(3.0) * &x[0]);
+    let error = grad_check(f, &inputs, 1e-5);
+    assert!(error < 1e-6, "error: {}", error);
+
+    // a deliberately wrong gradient is caught
+    let wrong = |x: &[Value]| &x[0].detach() * &x[1];
+    assert!(grad_check(wrong, &inputs[..2], 1e-5) > 0.1);
+}
@@ -1,12 +1,104 @@
 use std::{
-    cell::RefCell,
-    ops::{Add, Deref, DerefMut, Div, Mul, Neg, Sub},
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
     rc::Rc,
-    vec,
 };
 
+pub mod data;
+pub mod functional;
+pub mod gradcheck;
+pub mod loss;
+pub mod metrics;
 pub mod mlp;
 pub mod ops;
+pub mod optim;
+pub mod rnn;
+pub mod tensor;
+pub mod trainer;
+
+thread_local! {
+    static NO_GRAD: Cell<bool> = const { Cell::new(false) };
+    static BACKWARD_OPS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `f` without recording the computation graph.
+///
+/// Every operation inside produces a plain leaf holding only its result, so
+/// intermediate nodes are dropped as soon as they go out of scope instead of
+/// being kept alive by their consumers. This makes inference cheaper in both
+/// memory and time, at the cost that nothing computed inside can be
+/// backpropagated into its inputs.
+pub fn with_no_grad<R>(f: impl FnOnce() -> R) -> R {
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            NO_GRAD.with(|no_grad| no_grad.set(self.0));
+        }
+    }
+    let _reset = Reset(NO_GRAD.with(|no_grad| no_grad.replace(true)));
+    f()
+}
+
+/// Reads the data out of each value.
+pub fn values_to_f64(values: &[Value]) -> Vec<f64> {
+    values.iter().map(|value| value.data()).collect()
+}
+
+/// Sums by adding neighbouring pairs level by level, giving a graph of
+/// logarithmic depth. The empty sum is `0.0`.
+pub(crate) fn tree_sum(mut values: Vec<Value>) -> Value {
+    if values.is_empty() {
+        return Value::new(0.0);
+    }
+    while values.len() > 1 {
+        values = values
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => a + b,
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    values.pop().unwrap()
+}
+
+/// Sum of `values` as a balanced tree of additions, so the graph stays
+/// shallow even for long inputs. The empty sum is `0.0`.
+pub fn sum(values: &[Value]) -> Value {
+    tree_sum(values.to_vec())
+}
+
+/// Mean of `values`; the length is divided out as a constant. Panics on an
+/// empty slice.
+pub fn mean(values: &[Value]) -> Value {
+    assert!(!values.is_empty(), "mean of an empty slice");
+    &sum(values) / values.len() as f64
+}
+
+/// `base^exponent`, going through `powi` for integral exponents so that
+/// negative bases are raised exactly; other exponents of a negative base
+/// give NaN, as with `powf`.
+fn pow_f64(base: f64, exponent: f64) -> f64 {
+    if exponent.fract() == 0.0 && exponent.abs() <= i32::MAX as f64 {
+        base.powi(exponent as i32)
+    } else {
+        base.powf(exponent)
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    // Branch on the sign so `exp` only ever sees non-positive arguments.
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Operator {
@@ -17,9 +109,33 @@ pub enum Operator {
     Pow,
     Tanh,
     Exp,
+    Relu,
+    Log,
+    Div,
+    Sqrt,
+    Abs,
+    Max,
+    Min,
+    Clamp(f64, f64),
+    LeakyRelu(f64),
+    Sin,
+    Cos,
+    Sigmoid,
+    Elu(f64),
+    Softplus,
+    Powi(i32),
+    Swish,
+    Recip,
+    CrossEntropy(usize),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// A scalar node in the computation graph.
+///
+/// Comparison and equality only look at `data`, so outputs can be ranked
+/// and compared directly, in constant time, and `a == b` agrees with
+/// `a.partial_cmp(&b)`. Use [`Value::ptr_eq`] to ask whether two handles are
+/// the same node, or [`Value::structural_eq`] to compare whole histories.
+#[derive(Clone, Debug)]
 pub struct Value(Rc<RefCell<ValueInt>>);
 
 impl Value {
@@ -29,6 +145,86 @@ impl Value {
             operator: Operator::None,
             prev: vec![],
             grad: 0.0,
+            requires_grad: true,
+            name: None,
+        })))
+    }
+
+    /// A leaf carrying a label, shown by `get_ops` and `to_dot` so that
+    /// parameters can be told apart.
+    pub fn named(data: f64, name: &str) -> Self {
+        let value = Value::new(data);
+        value.0.borrow_mut().name = Some(name.to_string());
+        value
+    }
+
+    /// A leaf that never accumulates gradient, for constants that aren't
+    /// meant to be trained. It forwards its data like any other value.
+    pub fn no_grad_leaf(data: f64) -> Self {
+        let value = Value::new(data);
+        value.0.borrow_mut().requires_grad = false;
+        value
+    }
+
+    /// `n` fresh leaves holding `0.0`.
+    pub fn zeros(n: usize) -> Vec<Value> {
+        (0..n).map(|_| Value::new(0.0)).collect()
+    }
+
+    /// `n` fresh leaves holding `1.0`.
+    pub fn ones(n: usize) -> Vec<Value> {
+        (0..n).map(|_| Value::new(1.0)).collect()
+    }
+
+    /// Whether both handles point at the same graph node.
+    pub fn ptr_eq(&self, other: &Value) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Whether both graphs have the same shape: data, grad, operator, name
+    /// and inputs agree at every node. Walks both graphs iteratively and
+    /// compares each pair of shared nodes once.
+    pub fn structural_eq(&self, other: &Value) -> bool {
+        type Ptr = *const RefCell<ValueInt>;
+        let mut seen: HashSet<(Ptr, Ptr)> = HashSet::new();
+        let mut stack = vec![(self.clone(), other.clone())];
+        while let Some((a, b)) = stack.pop() {
+            if a.ptr_eq(&b) || !seen.insert((Rc::as_ptr(&a.0), Rc::as_ptr(&b.0))) {
+                continue;
+            }
+            let (a, b) = (a.0.borrow(), b.0.borrow());
+            if a.data != b.data
+                || a.grad != b.grad
+                || a.operator != b.operator
+                || a.requires_grad != b.requires_grad
+                || a.name != b.name
+                || a.prev.len() != b.prev.len()
+            {
+                return false;
+            }
+            stack.extend(a.prev.iter().cloned().zip(b.prev.iter().cloned()));
+        }
+        true
+    }
+
+    /// Wraps each number in a fresh leaf.
+    pub fn vec_from(data: &[f64]) -> Vec<Value> {
+        data.iter().map(|&x| Value::new(x)).collect()
+    }
+
+    /// Builds the result node of an operation, or a bare leaf inside
+    /// [`with_no_grad`].
+    pub(crate) fn from_op(data: f64, operator: Operator, prev: Vec<Value>) -> Self {
+        if NO_GRAD.with(|no_grad| no_grad.get()) {
+            return Value::new(data);
+        }
+        Value(Rc::new(RefCell::new(ValueInt {
+            data,
+            operator,
+            prev,
+            grad: 0.0,
+            requires_grad: true,
+            name: None,
         })))
     }
 
@@ -36,127 +232,569 @@ impl Value {
         self.0.borrow_mut().grad = 0.0;
     }
 
+    /// Resets the gradient of this node and of every node it depends on.
+    pub fn zero_grad_graph(&mut self) {
+        for node in self.topo_order() {
+            node.0.borrow_mut().grad = 0.0;
+        }
+    }
+
     pub fn set_data(&mut self, data: f64) {
         self.0.borrow_mut().data = data;
     }
 
+    /// Adds `delta` to the data in place, e.g. `param.update_data(-lr * grad)`.
+    pub fn update_data(&self, delta: f64) {
+        self.0.borrow_mut().data += delta;
+    }
+
+    /// Renders the expression that produced this value. Named nodes print
+    /// their name, leaves their data, unary operators as `op(x)` and binary
+    /// ones infix as `(a op b)`.
     pub fn get_ops(&self) -> String {
         let value_int = self.0.borrow();
-        if value_int.operator == Operator::None {
-            return value_int.data.to_string();
-        } else {
-            let mut result = String::new();
-            result.push('(');
-            result.push_str(&value_int.prev[0].get_ops());
-
-            match value_int.operator {
-                Operator::Add => result.push('+'),
-                Operator::Sub => result.push('-'),
-                Operator::Mul => result.push('*'),
-                Operator::Pow => result.push('^'),
-                Operator::Tanh => result.push_str("tanh("),
-                Operator::Exp => result.push_str("exp("),
-                Operator::None => {}
+        if let Some(name) = &value_int.name {
+            return name.clone();
+        }
+        let prev = &value_int.prev;
+        let unary = |name: &str| format!("{}({})", name, prev[0].get_ops());
+        let binary = |op: &str| format!("({}{}{})", prev[0].get_ops(), op, prev[1].get_ops());
+        match value_int.operator {
+            Operator::None => value_int.data.to_string(),
+            Operator::Add => binary("+"),
+            Operator::Sub => binary("-"),
+            Operator::Mul => binary("*"),
+            Operator::Div => binary("/"),
+            Operator::Pow => binary("^"),
+            Operator::Powi(n) => format!("({}^{})", prev[0].get_ops(), n),
+            Operator::Max => format!("max({}, {})", prev[0].get_ops(), prev[1].get_ops()),
+            Operator::Min => format!("min({}, {})", prev[0].get_ops(), prev[1].get_ops()),
+            Operator::Clamp(lo, hi) => format!("clamp({}, {}, {})", prev[0].get_ops(), lo, hi),
+            Operator::Tanh => unary("tanh"),
+            Operator::Exp => unary("exp"),
+            Operator::Relu => unary("relu"),
+            Operator::Log => unary("log"),
+            Operator::Sqrt => unary("sqrt"),
+            Operator::Abs => unary("abs"),
+            Operator::Sigmoid => unary("sigmoid"),
+            Operator::Swish => unary("swish"),
+            Operator::Softplus => unary("softplus"),
+            Operator::Sin => unary("sin"),
+            Operator::Cos => unary("cos"),
+            Operator::Recip => unary("recip"),
+            Operator::Elu(_) => unary("elu"),
+            Operator::LeakyRelu(_) => unary("leaky_relu"),
+            Operator::CrossEntropy(target) => {
+                let logits: Vec<String> = prev.iter().map(|logit| logit.get_ops()).collect();
+                format!("cross_entropy([{}], {})", logits.join(", "), target)
             }
+        }
+    }
 
-            result.push_str(&value_int.prev[1].get_ops());
+    /// Renders the graph as Graphviz DOT. Every node appears once, labelled
+    /// with its operator, data and grad, with an edge to each of its inputs.
+    pub fn to_dot(&self) -> String {
+        let topo = self.topo_order();
+        let ids: HashMap<*const RefCell<ValueInt>, usize> = topo
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (Rc::as_ptr(&node.0), i))
+            .collect();
 
-            if value_int.operator == Operator::Tanh || value_int.operator == Operator::Exp {
-                result.push(')');
+        let mut result = String::from("digraph {\n");
+        for (i, node) in topo.iter().enumerate() {
+            let value_int = node.0.borrow();
+            let title = match &value_int.name {
+                // names are free-form, so keep them from closing the label
+                Some(name) => name.replace('\\', "\\\\").replace('"', "\\\""),
+                None => format!("{:?}", value_int.operator),
+            };
+            result.push_str(&format!(
+                "    n{} [label=\"{}\\ndata={}\\ngrad={}\"];\n",
+                i, title, value_int.data, value_int.grad
+            ));
+        }
+        for (i, node) in topo.iter().enumerate() {
+            for child in node.0.borrow().prev.iter() {
+                result.push_str(&format!("    n{} -> n{};\n", i, ids[&Rc::as_ptr(&child.0)]));
             }
-            result.push(')');
-            result
         }
+        result.push('}');
+        result
+    }
+
+    /// Returns a new leaf with the same data, so backward stops here. The
+    /// original graph is left as is.
+    pub fn detach(&self) -> Value {
+        Value::new(self.0.borrow().data)
+    }
+
+    /// Returns a new leaf holding `f(data)`. The result is cut off from the
+    /// graph, so no gradient flows back through `f`; meant for fixed
+    /// preprocessing such as input normalization.
+    pub fn map_data(&self, f: impl Fn(f64) -> f64) -> Value {
+        Value::new(f(self.0.borrow().data))
     }
 
     pub fn tanh(&self) -> Value {
         let data = self.0.borrow().data.tanh();
-        let operator = Operator::Tanh;
-        Value(Rc::new(RefCell::new(ValueInt {
-            data,
-            operator,
-            prev: vec![self.clone()],
-            grad: 0.0,
-        })))
+        Value::from_op(data, Operator::Tanh, vec![self.clone()])
     }
 
     pub fn exp(&self) -> Value {
         let data = self.0.borrow().data.exp();
-        let operator = Operator::Exp;
-        Value(Rc::new(RefCell::new(ValueInt {
-            data,
-            operator,
-            prev: vec![self.clone()],
-            grad: 0.0,
-        })))
+        Value::from_op(data, Operator::Exp, vec![self.clone()])
+    }
+
+    pub fn relu(&self) -> Value {
+        let data = self.0.borrow().data.max(0.0);
+        Value::from_op(data, Operator::Relu, vec![self.clone()])
+    }
+
+    pub fn sin(&self) -> Value {
+        let data = self.0.borrow().data.sin();
+        Value::from_op(data, Operator::Sin, vec![self.clone()])
+    }
+
+    pub fn cos(&self) -> Value {
+        let data = self.0.borrow().data.cos();
+        Value::from_op(data, Operator::Cos, vec![self.clone()])
+    }
+
+    /// `x` for positive inputs, `slope * x` otherwise.
+    pub fn leaky_relu(&self, slope: f64) -> Value {
+        let x = self.0.borrow().data;
+        let data = if x > 0.0 { x } else { slope * x };
+        Value::from_op(data, Operator::LeakyRelu(slope), vec![self.clone()])
     }
 
+    /// `x` for positive inputs, `alpha * (exp(x) - 1)` otherwise.
+    pub fn elu(&self, alpha: f64) -> Value {
+        let x = self.0.borrow().data;
+        let data = if x > 0.0 { x } else { alpha * (x.exp() - 1.0) };
+        Value::from_op(data, Operator::Elu(alpha), vec![self.clone()])
+    }
+
+    /// Natural logarithm. Like `f64::ln`, this yields `-inf` at `0.0` and
+    /// `NaN` for negative inputs rather than panicking, and the gradient
+    /// follows suit.
+    pub fn log(&self) -> Value {
+        let data = self.0.borrow().data.ln();
+        Value::from_op(data, Operator::Log, vec![self.clone()])
+    }
+
+    pub fn sqrt(&self) -> Value {
+        let data = self.0.borrow().data.sqrt();
+        Value::from_op(data, Operator::Sqrt, vec![self.clone()])
+    }
+
+    /// `1 / self`, with its own backward rule rather than going through
+    /// division or `pow(-1.0)`.
+    pub fn recip(&self) -> Value {
+        let data = 1.0 / self.0.borrow().data;
+        Value::from_op(data, Operator::Recip, vec![self.clone()])
+    }
+
+    /// Absolute value. The subgradient at exactly `0.0` is taken to be `0.0`.
+    pub fn abs(&self) -> Value {
+        let data = self.0.borrow().data.abs();
+        Value::from_op(data, Operator::Abs, vec![self.clone()])
+    }
+
+    /// The larger of the two values. On a tie `self` is selected, and only the
+    /// selected operand receives gradient.
+    pub fn max(&self, other: &Value) -> Value {
+        let data = self.0.borrow().data.max(other.0.borrow().data);
+        Value::from_op(data, Operator::Max, vec![self.clone(), other.clone()])
+    }
+
+    /// The smaller of the two values. On a tie `self` is selected, and only
+    /// the selected operand receives gradient.
+    pub fn min(&self, other: &Value) -> Value {
+        let data = self.0.borrow().data.min(other.0.borrow().data);
+        Value::from_op(data, Operator::Min, vec![self.clone(), other.clone()])
+    }
+
+    /// Restricts data to `[lo, hi]`. Gradient only passes through when the
+    /// input lies strictly inside `(lo, hi)`.
+    pub fn clamp(&self, lo: f64, hi: f64) -> Value {
+        let data = self.0.borrow().data.clamp(lo, hi);
+        Value::from_op(data, Operator::Clamp(lo, hi), vec![self.clone()])
+    }
+
+    /// Logistic function, computed without overflowing `exp` for large
+    /// inputs of either sign.
     pub fn sigmoid(&self) -> Value {
-        &Value::new(1.0) / &(&Value::new(1.0) + &(-self).exp())
+        let data = sigmoid(self.0.borrow().data);
+        Value::from_op(data, Operator::Sigmoid, vec![self.clone()])
+    }
+
+    /// Swish (SiLU), `x * sigmoid(x)`.
+    pub fn swish(&self) -> Value {
+        let x = self.0.borrow().data;
+        let data = x * sigmoid(x);
+        Value::from_op(data, Operator::Swish, vec![self.clone()])
+    }
+
+    /// GELU with the tanh approximation,
+    /// `0.5 * x * (1 + tanh(sqrt(2 / pi) * (x + 0.044715 * x^3)))`, composed
+    /// from existing operations.
+    pub fn gelu(&self) -> Value {
+        let inner = &(self + &(&self.powi(3) * 0.044715)) * (2.0 / std::f64::consts::PI).sqrt();
+        &(self * 0.5) * &(1.0 + &inner.tanh())
     }
 
+    /// `ln(1 + exp(x))`, computed as `max(x, 0) + ln(1 + exp(-|x|))` so that
+    /// large inputs don't overflow.
+    pub fn softplus(&self) -> Value {
+        let x = self.0.borrow().data;
+        let data = x.max(0.0) + (-x.abs()).exp().ln_1p();
+        Value::from_op(data, Operator::Softplus, vec![self.clone()])
+    }
+
+    /// Backpropagates from this node, seeding it with gradient `1.0`. On a
+    /// fresh graph the node's own gradient is exactly `1.0` afterwards.
     pub fn backward(&mut self) {
-        self.0.borrow_mut().grad = 1.0;
-        self.set_grad(1.0);
+        self.backward_with(1.0);
+    }
+
+    /// Backpropagates from this node seeded with `seed` instead of `1.0`,
+    /// giving `seed` times every gradient [`Value::backward`] would. Seeding
+    /// one component of a vector output this way computes a
+    /// vector-Jacobian product.
+    pub fn backward_with(&mut self, seed: f64) {
+        self.set_grad(seed);
+    }
+
+    /// Backpropagates from this loss and adds the result to any gradient the
+    /// parameters already hold, so several losses sharing parameters can be
+    /// backpropagated one after another before a single optimizer step.
+    ///
+    /// This is exactly what [`Value::backward`] does; the name spells out the
+    /// contract for gradient accumulation loops. Call `zero_grad` on the
+    /// parameters once the accumulated gradient has been used.
+    pub fn backward_accumulate(&mut self) {
+        self.backward();
     }
 
+    /// Backpropagates `grad` from this node into every node it depends on.
+    ///
+    /// The graph is first sorted topologically so that each node applies its
+    /// local gradient rule exactly once, after all of its consumers have
+    /// contributed to it. Gradients from this pass are added to whatever is
+    /// already stored, so calling this repeatedly accumulates.
     pub fn set_grad(&mut self, grad: f64) {
-        let mut self_borrow_mut = self.0.borrow_mut();
-        self_borrow_mut.grad += grad;
-        let operator = self_borrow_mut.operator;
-        let mut prev = self_borrow_mut.prev.clone(); // Clone the previous values to avoid multiple borrows
+        let topo = self.topo_order();
+        let index: HashMap<*const RefCell<ValueInt>, usize> = topo
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (Rc::as_ptr(&node.0), i))
+            .collect();
+        let mut grads = vec![0.0; topo.len()];
+        grads[topo.len() - 1] = grad;
+        let mut ops = 0;
 
-        drop(self_borrow_mut); // Explicitly drop the mutable borrow
+        for (i, node) in topo.iter().enumerate().rev() {
+            let grad = grads[i];
+            let mut node_borrow_mut = node.0.borrow_mut();
+            if !node_borrow_mut.requires_grad {
+                continue;
+            }
+            ops += 1;
+            node_borrow_mut.grad += grad;
+            let prev = node_borrow_mut.prev.clone(); // Clone the previous values to avoid multiple borrows
+
+            drop(node_borrow_mut); // Explicitly drop the mutable borrow
 
-        match operator {
-            Operator::Exp => {
-                let data = prev[0].0.borrow().data;
-                prev[0].set_grad(grad * data.exp());
+            for (child, local) in prev.iter().zip(node.local_grad()) {
+                if child.0.borrow().requires_grad {
+                    grads[index[&Rc::as_ptr(&child.0)]] += grad * local;
+                }
             }
-            Operator::Add => {
-                prev[0].set_grad(grad);
-                prev[1].set_grad(grad);
+        }
+        BACKWARD_OPS.with(|count| count.set(ops));
+    }
+
+    /// How many nodes the most recent backward pass on this thread applied
+    /// a gradient to. Each node is processed once however many paths lead
+    /// to it, so this is at most the size of the graph.
+    pub fn last_backward_op_count() -> usize {
+        BACKWARD_OPS.with(|count| count.get())
+    }
+
+    /// Partial derivatives of this node with respect to each of its inputs,
+    /// in `prev` order; for a product `a * b` this is `[b, a]`. Leaves have
+    /// none. The backward pass scales these by the incoming gradient.
+    pub fn local_grad(&self) -> Vec<f64> {
+        let value_int = self.0.borrow();
+        let out = value_int.data;
+        let prev = &value_int.prev;
+        let data = |i: usize| prev[i].0.borrow().data;
+        match value_int.operator {
+            Operator::None => vec![],
+            Operator::Add => vec![1.0, 1.0],
+            Operator::Sub => vec![1.0, -1.0],
+            Operator::Mul => vec![data(1), data(0)],
+            Operator::Div => vec![1.0 / data(1), -data(0) / (data(1) * data(1))],
+            Operator::Pow => {
+                let (base, exponent) = (data(0), data(1));
+                // d/dn a^n = a^n ln(a) only exists for a positive base
+                let d_exponent = if base > 0.0 { out * base.ln() } else { 0.0 };
+                vec![exponent * pow_f64(base, exponent - 1.0), d_exponent]
             }
-            Operator::Sub => {
-                prev[0].set_grad(grad);
-                prev[1].set_grad(-grad);
+            Operator::Powi(n) => vec![n as f64 * data(0).powi(n - 1)],
+            Operator::Exp => vec![out],
+            Operator::Tanh => vec![1.0 - out.powi(2)],
+            Operator::Relu => vec![if data(0) > 0.0 { 1.0 } else { 0.0 }],
+            Operator::LeakyRelu(slope) => vec![if data(0) > 0.0 { 1.0 } else { slope }],
+            Operator::Elu(alpha) => vec![if data(0) > 0.0 { 1.0 } else { out + alpha }],
+            Operator::Log => vec![1.0 / data(0)],
+            Operator::Sqrt => vec![0.5 / out],
+            Operator::Recip => vec![-out * out],
+            Operator::Abs => {
+                let x = data(0);
+                let sign = if x > 0.0 {
+                    1.0
+                } else if x < 0.0 {
+                    -1.0
+                } else {
+                    0.0
+                };
+                vec![sign]
             }
-            Operator::Mul => {
-                let data1 = prev[1].0.borrow().data;
-                let data0 = prev[0].0.borrow().data;
-                prev[0].set_grad(grad * data1);
-                prev[1].set_grad(grad * data0);
+            Operator::Sigmoid => vec![out * (1.0 - out)],
+            Operator::Swish => {
+                let x = data(0);
+                let s = sigmoid(x);
+                vec![s + x * s * (1.0 - s)]
             }
-            Operator::Pow => {
-                let data1 = prev[1].0.borrow().data;
-                let data0 = prev[0].0.borrow().data;
-                prev[0].set_grad(grad * data1 * data0.powf(data1 - 1.0));
+            Operator::Softplus => vec![sigmoid(data(0))],
+            Operator::Sin => vec![data(0).cos()],
+            Operator::Cos => vec![-data(0).sin()],
+            Operator::Clamp(lo, hi) => {
+                let x = data(0);
+                vec![if lo < x && x < hi { 1.0 } else { 0.0 }]
+            }
+            Operator::CrossEntropy(target) => {
+                // softmax(logits) - onehot(target)
+                let max = prev
+                    .iter()
+                    .map(|logit| logit.0.borrow().data)
+                    .fold(f64::NEG_INFINITY, f64::max);
+                let exps: Vec<f64> = prev
+                    .iter()
+                    .map(|logit| (logit.0.borrow().data - max).exp())
+                    .collect();
+                let total: f64 = exps.iter().sum();
+                exps.iter()
+                    .enumerate()
+                    .map(|(i, e)| e / total - if i == target { 1.0 } else { 0.0 })
+                    .collect()
+            }
+            Operator::Max | Operator::Min => {
+                // only the selected operand receives gradient; ties go left
+                let left = if value_int.operator == Operator::Max {
+                    data(0) >= data(1)
+                } else {
+                    data(0) <= data(1)
+                };
+                if left {
+                    vec![1.0, 0.0]
+                } else {
+                    vec![0.0, 1.0]
+                }
+            }
+        }
+    }
+
+    /// Deep-copies the graph behind this value into fresh nodes that share
+    /// nothing with the original. Data, gradients, operators and names are
+    /// kept, and a node reached along several paths is copied once, so the
+    /// copy has the same DAG shape. Returns the copy of `self`.
+    pub fn clone_detached_graph(&self) -> Value {
+        let mut copies: HashMap<*const RefCell<ValueInt>, Value> = HashMap::new();
+        let mut copy = self.clone();
+        for node in self.topo_order() {
+            let value_int = node.0.borrow();
+            copy = Value(Rc::new(RefCell::new(ValueInt {
+                data: value_int.data,
+                operator: value_int.operator,
+                prev: value_int
+                    .prev
+                    .iter()
+                    .map(|child| copies[&Rc::as_ptr(&child.0)].clone())
+                    .collect(),
+                grad: value_int.grad,
+                requires_grad: value_int.requires_grad,
+                name: value_int.name.clone(),
+            })));
+            copies.insert(Rc::as_ptr(&node.0), copy.clone());
+        }
+        // topo_order ends with `self`
+        copy
+    }
+
+    /// Returns every node reachable from `self`, children before parents,
+    /// with `self` last; backward walks this list from the end. Shared nodes
+    /// appear once, keyed on their `Rc` allocation. Panics if the graph
+    /// contains a cycle.
+    pub fn topo_order(&self) -> Vec<Value> {
+        let mut topo = Vec::new();
+        let mut visited: HashSet<*const RefCell<ValueInt>> = HashSet::new();
+        // Nodes whose children are still being explored, i.e. the current
+        // DFS path. Reaching one of them again means there is a cycle.
+        let mut in_progress: HashSet<*const RefCell<ValueInt>> = HashSet::new();
+        // Iterative DFS so deep graphs don't overflow the stack. The flag is
+        // set once a node's children have all been pushed.
+        let mut stack = vec![(self.clone(), false)];
+        while let Some((node, expanded)) = stack.pop() {
+            if expanded {
+                in_progress.remove(&Rc::as_ptr(&node.0));
+                topo.push(node);
+                continue;
+            }
+            if !visited.insert(Rc::as_ptr(&node.0)) {
+                continue;
             }
-            Operator::Tanh => {
-                let data = prev[0].0.borrow().data;
-                prev[0].set_grad(grad * (1.0 - data.tanh().powi(2)));
+            in_progress.insert(Rc::as_ptr(&node.0));
+            let prev = node.0.borrow().prev.clone();
+            stack.push((node, true));
+            for child in prev.into_iter().rev() {
+                let ptr = Rc::as_ptr(&child.0);
+                if in_progress.contains(&ptr) {
+                    panic!("cycle detected in computation graph");
+                }
+                if !visited.contains(&ptr) {
+                    stack.push((child, false));
+                }
             }
-            Operator::None => {}
         }
+        topo
     }
 
     pub fn data(&self) -> f64 {
         self.0.borrow().data
     }
 
+    #[deprecated(note = "use `grad_f64`, which doesn't allocate a new `Value`")]
     pub fn grad(&self) -> Value {
         self.0.borrow().grad.to_value()
     }
 
+    pub fn grad_f64(&self) -> f64 {
+        self.0.borrow().grad
+    }
+
+    /// Whether `data` is neither infinite nor NaN.
+    pub fn is_finite(&self) -> bool {
+        self.0.borrow().data.is_finite()
+    }
+
+    /// Whether `data` is within `tol` of `expected`.
+    pub fn approx_eq(&self, expected: f64, tol: f64) -> bool {
+        (self.0.borrow().data - expected).abs() <= tol
+    }
+
+    /// Whether the stored gradient is within `tol` of `expected`.
+    pub fn grad_approx_eq(&self, expected: f64, tol: f64) -> bool {
+        (self.0.borrow().grad - expected).abs() <= tol
+    }
+
+    /// Overwrites the stored gradient of this node only.
+    pub fn set_grad_value(&self, grad: f64) {
+        self.0.borrow_mut().grad = grad;
+    }
+
+    /// The inputs this node was computed from; empty for leaves.
+    pub fn prev(&self) -> Vec<Value> {
+        self.0.borrow().prev.clone()
+    }
+
+    pub fn operator(&self) -> Operator {
+        self.0.borrow().operator
+    }
+
+    /// Whether this is an input to the graph rather than the result of an
+    /// operation: parameters and constants are leaves, intermediates aren't.
+    pub fn is_leaf(&self) -> bool {
+        let value_int = self.0.borrow();
+        value_int.operator == Operator::None && value_int.prev.is_empty()
+    }
+
+    /// `self^n`. Any exponent is supported for a positive base; a negative
+    /// base needs an integral exponent, and otherwise the data and gradient
+    /// are both NaN.
     pub fn pow(&self, n: f64) -> Value {
-        let data = self.0.borrow().data.powf(n);
-        let operator = Operator::Pow;
-        Value(Rc::new(RefCell::new(ValueInt {
+        let data = pow_f64(self.0.borrow().data, n);
+        Value::from_op(
             data,
-            operator,
-            prev: vec![self.clone(), Value::new(n)],
-            grad: 0.0,
-        })))
+            Operator::Pow,
+            vec![self.clone(), Value::no_grad_leaf(n)],
+        )
+    }
+
+    /// `sum(weights[i] * inputs[i])`, reduced as a balanced tree of additions
+    /// so the graph is O(log n) deep instead of a left fold's O(n).
+    pub fn dot(weights: &[Value], inputs: &[Value]) -> Value {
+        assert_eq!(weights.len(), inputs.len());
+        let products = weights
+            .iter()
+            .zip(inputs.iter())
+            .map(|(w, x)| w * x)
+            .collect();
+        tree_sum(products)
+    }
+
+    /// Integer power, using `f64::powi` in both directions.
+    pub fn powi(&self, n: i32) -> Value {
+        let data = self.0.borrow().data.powi(n);
+        Value::from_op(data, Operator::Powi(n), vec![self.clone()])
+    }
+
+    /// Like [`Value::pow`] with a `Value` exponent, which also receives
+    /// gradient `a^n * ln(a)`. That term is skipped for non-positive bases,
+    /// where the logarithm is undefined.
+    pub fn powv(&self, n: &Value) -> Value {
+        let data = pow_f64(self.0.borrow().data, n.0.borrow().data);
+        Value::from_op(data, Operator::Pow, vec![self.clone(), n.clone()])
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value_int = self.0.borrow();
+        write!(f, "Value(data={}, grad={})", value_int.data, value_int.grad)
+    }
+}
+
+/// A `Value` that hashes and compares by node identity rather than by
+/// contents, for use as a `HashSet`/`HashMap` key.
+#[derive(Clone, Debug)]
+pub struct ValuePtr(pub Value);
+
+impl PartialEq for ValuePtr {
+    fn eq(&self, other: &ValuePtr) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl Eq for ValuePtr {}
+
+impl Hash for ValuePtr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0 .0).hash(state);
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.data() == other.data()
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        self.data().partial_cmp(&other.data())
     }
 }
 
@@ -176,31 +814,26 @@ impl ToValue for f32 {
     }
 }
 
-impl Deref for Value {
-    type Target = Rc<RefCell<ValueInt>>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl DerefMut for Value {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
-
-#[derive(PartialEq, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct ValueInt {
     data: f64,
     operator: Operator,
     prev: Vec<Value>,
     grad: f64,
+    requires_grad: bool,
+    name: Option<String>,
 }
 
-impl Into<Value> for f64 {
-    fn into(self) -> Value {
-        Value::new(self)
+impl Default for Value {
+    /// A fresh `0.0` leaf.
+    fn default() -> Self {
+        Value::new(0.0)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(data: f64) -> Value {
+        Value::new(data)
     }
 }
 
@@ -223,10 +856,10 @@ fn test_operator() {
     // g = (d/b) - ac
     // check grad
     g.backward();
-    println!("a.grad: {}", a.0.borrow().grad); // -c = -3
-    assert_eq!(a.0.borrow().grad, -3.0);
-    println!("b.grad: {}", b.0.borrow().grad);
-    assert_eq!(b.0.borrow().grad, 4.0);
+    println!("a.grad: {}", a.grad_f64()); // -c = -3
+    assert!(a.grad_approx_eq(-3.0, 1e-12));
+    println!("b.grad: {}", b.grad_f64());
+    assert!(b.grad_approx_eq(4.0, 1e-12));
 }
 
 #[test]
@@ -240,10 +873,10 @@ fn test_pow() {
     // g = a^2 - b^3
     // check grad
     g.backward();
-    println!("a.grad: {}", a.0.borrow().grad); // 2a = 4
-    assert_eq!(a.0.borrow().grad, 4.0);
-    println!("b.grad: {}", b.0.borrow().grad); // -3b^2 = -27
-    assert_eq!(b.0.borrow().grad, -27.0);
+    println!("a.grad: {}", a.grad_f64()); // 2a = 4
+    assert!(a.grad_approx_eq(4.0, 1e-12));
+    println!("b.grad: {}", b.grad_f64()); // -3b^2 = -27
+    assert!(b.grad_approx_eq(-27.0, 1e-12));
 }
 
 #[test]
@@ -258,7 +891,30 @@ fn neuron() {
     let y = &(&x1w1 + &x2w2) + &b;
     let mut o = y.tanh();
     o.backward();
-    println!("y.grad: {}", y.0.borrow().grad); // ~ 0.5
+    println!("y.grad: {}", y.grad_f64()); // ~ 0.5
+
+    let dot = o.to_dot();
+    assert!(dot.starts_with("digraph {"));
+    assert_eq!(dot.matches("[label=").count(), 10);
+    assert_eq!(dot.matches(" -> ").count(), 9);
+}
+
+#[test]
+fn test_to_dot_shared() {
+    let a = Value::new(2.0);
+    let s = &a + &a;
+    let y = &s * &s;
+    let dot = y.to_dot();
+    // a and s appear once each, with two edges apiece into them
+    assert_eq!(dot.matches("[label=").count(), 3);
+    assert_eq!(dot.matches(" -> ").count(), 4);
+}
+
+#[test]
+fn test_to_dot_escapes_names() {
+    let a = Value::named(1.0, r#"say "hi" \ bye"#);
+    let dot = a.to_dot();
+    assert!(dot.contains(r#"label="say \"hi\" \\ bye\ndata="#));
 }
 
 #[test]
@@ -266,11 +922,776 @@ fn test_same_value() {
     let a = Value::new(1.0);
     let mut c = &a + &a;
     c.backward();
-    println!("a.grad: {}", a.0.borrow().grad); // 2
-    assert_eq!(a.0.borrow().grad, 2.0);
+    println!("a.grad: {}", a.grad_f64()); // 2
+    assert!(a.grad_approx_eq(2.0, 1e-12));
     let a = Value::new(3.0);
     let mut c = &a * &a;
     c.backward();
-    println!("a.grad: {}", a.0.borrow().grad); // 6
-    assert_eq!(a.0.borrow().grad, 6.0);
+    println!("a.grad: {}", a.grad_f64()); // 6
+    assert!(a.grad_approx_eq(6.0, 1e-12));
+}
+
+#[test]
+fn test_diamond() {
+    let a = Value::new(2.0);
+    let b = Value::new(3.0);
+    let s = &a + &b;
+    let t = s.tanh();
+    let u = s.exp();
+    let mut y = &t * &u;
+    // y = tanh(a+b) * exp(a+b)
+    // dy/da = dy/db = (1 - tanh(s)^2) * exp(s) + tanh(s) * exp(s)
+    y.backward();
+    let sd = 5.0_f64;
+    let expected = (1.0 - sd.tanh().powi(2)) * sd.exp() + sd.tanh() * sd.exp();
+    assert!(a.grad_approx_eq(expected, 1e-9));
+    assert!(b.grad_approx_eq(expected, 1e-9));
+    assert!(s.grad_approx_eq(expected, 1e-9));
+
+    let a = Value::new(2.0);
+    let b = Value::new(3.0);
+    let s = &a + &b;
+    let mut y = &s * &s;
+    // y = (a+b)^2, dy/da = 2(a+b) = 10
+    y.backward();
+    assert!(a.grad_approx_eq(10.0, 1e-12));
+    assert!(b.grad_approx_eq(10.0, 1e-12));
+}
+
+#[test]
+fn test_relu() {
+    let a = Value::new(2.0);
+    let b = Value::new(3.0);
+    let mut y = &a.relu() * &b;
+    assert!(y.approx_eq(6.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(3.0, 1e-12)); // 1.0 * upstream
+    assert_eq!(a.relu().get_ops(), "relu(2)");
+
+    let a = Value::new(-2.0);
+    let mut y = &a.relu() * &b;
+    assert!(y.approx_eq(0.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_log() {
+    let a = Value::new(4.0);
+    let mut y = a.log();
+    assert!(y.approx_eq(4.0_f64.ln(), 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(0.25, 1e-12));
+    assert_eq!(y.get_ops(), "log(4)");
+
+    assert_eq!(Value::new(0.0).log().data(), f64::NEG_INFINITY);
+    assert!(Value::new(-1.0).log().data().is_nan());
+}
+
+#[test]
+fn test_zero_grad_graph() {
+    let a = Value::new(2.0);
+    let b = Value::new(3.0);
+    let c = &a * &b;
+    let d = c.tanh();
+    let mut e = &d + &a;
+    e.backward();
+    assert_ne!(a.grad_f64(), 0.0);
+    assert_ne!(c.grad_f64(), 0.0);
+
+    e.zero_grad_graph();
+    for node in [&a, &b, &c, &d, &e] {
+        assert!(node.grad_approx_eq(0.0, 1e-12));
+    }
+}
+
+#[test]
+fn test_div() {
+    let a = Value::new(6.0);
+    let b = Value::new(2.0);
+    let mut y = &a / &b;
+    assert!(y.approx_eq(3.0, 1e-12));
+    assert_eq!(y.get_ops(), "(6/2)");
+    y.backward();
+    assert!(a.grad_approx_eq(0.5, 1e-12)); // 1/b
+    assert!(b.grad_approx_eq(-1.5, 1e-12)); // -a/b^2
+}
+
+#[test]
+fn test_display() {
+    let a = Value::new(2.0);
+    let mut y = &a * &a;
+    y.backward();
+    assert_eq!(format!("{}", y), "Value(data=4, grad=1)");
+    assert_eq!(a.to_string(), "Value(data=2, grad=4)");
+}
+
+#[test]
+fn test_activation_uses_output() {
+    for x in [-1.5, 0.0, 0.3, 2.0] {
+        let a = Value::new(x);
+        let mut y = a.exp();
+        y.backward();
+        assert!(a.grad_approx_eq(x.exp(), 1e-12));
+
+        let a = Value::new(x);
+        let mut y = a.tanh();
+        y.backward();
+        assert!(a.grad_approx_eq(1.0 - x.tanh().powi(2), 1e-12));
+    }
+}
+
+#[test]
+fn test_detach() {
+    let a = Value::new(2.0);
+    let b = Value::new(5.0);
+    let mut y = &a.detach() * &b;
+    y.backward();
+    assert!(a.grad_approx_eq(0.0, 1e-12));
+    assert!(b.grad_approx_eq(a.data(), 1e-12));
+}
+
+#[test]
+fn test_partial_ord() {
+    let mut values = [Value::new(3.0), Value::new(-1.0), Value::new(2.0)];
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let data: Vec<f64> = values.iter().map(|v| v.data()).collect();
+    assert_eq!(data, vec![-1.0, 2.0, 3.0]);
+
+    let max = values.iter().max_by(|a, b| a.partial_cmp(b).unwrap());
+    assert!(max.unwrap().approx_eq(3.0, 1e-12));
+    assert!(Value::new(1.0) < Value::new(2.0));
+}
+
+#[test]
+fn test_sqrt() {
+    let a = Value::new(9.0);
+    let mut y = a.sqrt();
+    assert!(y.approx_eq(3.0, 1e-12));
+    assert_eq!(y.get_ops(), "sqrt(9)");
+    y.backward();
+    assert!(a.grad_approx_eq(1.0 / 6.0, 1e-12));
+}
+
+#[test]
+fn test_abs() {
+    for (x, expected_grad) in [(3.0, 1.0), (-3.0, -1.0), (0.0, 0.0)] {
+        let a = Value::new(x);
+        let mut y = a.abs();
+        assert!(y.approx_eq(f64::abs(x), 1e-12));
+        y.backward();
+        assert!(a.grad_approx_eq(expected_grad, 1e-12));
+    }
+    assert_eq!(Value::new(-3.0).abs().get_ops(), "abs(-3)");
+}
+
+#[test]
+fn test_grad_f64() {
+    let a = Value::new(3.0);
+    let mut y = &a * &a;
+    y.backward();
+    assert!(a.grad_approx_eq(6.0, 1e-12));
+    #[allow(deprecated)]
+    let wrapped = f64::from(a.grad());
+    assert!(a.grad_approx_eq(wrapped, 1e-12));
+}
+
+#[test]
+fn test_max_min() {
+    let a = Value::new(2.0);
+    let b = Value::new(5.0);
+    let mut y = a.max(&b);
+    assert!(y.approx_eq(5.0, 1e-12));
+    assert_eq!(y.get_ops(), "max(2, 5)");
+    y.backward();
+    assert!(a.grad_approx_eq(0.0, 1e-12));
+    assert!(b.grad_approx_eq(1.0, 1e-12));
+
+    let a = Value::new(2.0);
+    let b = Value::new(5.0);
+    let mut y = a.min(&b);
+    assert!(y.approx_eq(2.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(1.0, 1e-12));
+    assert!(b.grad_approx_eq(0.0, 1e-12));
+
+    // ties go to the left operand
+    let a = Value::new(1.0);
+    let b = Value::new(1.0);
+    let mut y = &a.max(&b) + &a.min(&b);
+    y.backward();
+    assert!(a.grad_approx_eq(2.0, 1e-12));
+    assert!(b.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_clamp() {
+    for (x, expected_data, expected_grad) in [(0.5, 0.5, 1.0), (-3.0, -1.0, 0.0), (3.0, 1.0, 0.0)] {
+        let a = Value::new(x);
+        let mut y = a.clamp(-1.0, 1.0);
+        assert!(y.approx_eq(expected_data, 1e-12));
+        y.backward();
+        assert!(a.grad_approx_eq(expected_grad, 1e-12));
+    }
+    assert_eq!(
+        Value::new(0.5).clamp(-1.0, 1.0).get_ops(),
+        "clamp(0.5, -1, 1)"
+    );
+}
+
+#[test]
+fn test_powv() {
+    let a = Value::new(2.0);
+    let n = Value::new(3.0);
+    let mut y = a.powv(&n);
+    assert!(y.approx_eq(8.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(12.0, 1e-12)); // n * a^(n-1)
+    assert!(n.grad_approx_eq(8.0 * 2.0_f64.ln(), 1e-12)); // a^n ln(a)
+
+    let a = Value::new(-2.0);
+    let n = Value::new(2.0);
+    let mut y = a.powv(&n);
+    y.backward();
+    assert!(a.grad_approx_eq(-4.0, 1e-12));
+    assert!(n.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_no_grad() {
+    let x = Value::new(2.0);
+    let w = Value::new(3.0);
+    let expected = (&x * &w).tanh().data();
+
+    let mut y = with_no_grad(|| (&x * &w).tanh());
+    assert!(y.approx_eq(expected, 1e-12));
+    assert_eq!(y.0.borrow().operator, Operator::None);
+    assert!(y.0.borrow().prev.is_empty());
+    y.backward();
+    assert!(x.grad_approx_eq(0.0, 1e-12));
+    assert!(w.grad_approx_eq(0.0, 1e-12));
+
+    // graph building resumes afterwards
+    assert_eq!((&x * &w).0.borrow().prev.len(), 2);
+}
+
+#[test]
+fn test_vec_from() {
+    let data = [1.0, 2.0, 2.0];
+    let values = Value::vec_from(&data);
+    assert_eq!(values.len(), 3);
+    assert!(values
+        .iter()
+        .all(|v| v.0.borrow().operator == Operator::None));
+    assert_eq!(values_to_f64(&values), data);
+}
+
+#[test]
+fn test_ptr_eq() {
+    let a = Value::new(1.0);
+    let b = Value::new(1.0);
+    let a2 = a.clone();
+    assert_eq!(a, b); // same data
+    assert!(!a.ptr_eq(&b));
+    assert!(a.ptr_eq(&a2));
+
+    let set: HashSet<ValuePtr> = [a, b, a2].into_iter().map(ValuePtr).collect();
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn test_leaky_relu() {
+    let a = Value::new(2.0);
+    let mut y = a.leaky_relu(0.01);
+    assert!(y.approx_eq(2.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(1.0, 1e-12));
+
+    let a = Value::new(-2.0);
+    let mut y = a.leaky_relu(0.01);
+    assert!(y.approx_eq(-0.02, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(0.01, 1e-12));
+    assert_eq!(y.get_ops(), "leaky_relu(-2)");
+}
+
+#[test]
+fn test_sin_cos() {
+    let a = Value::new(0.0);
+    let mut y = a.sin();
+    assert!(y.approx_eq(0.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(1.0, 1e-12));
+
+    let a = Value::new(0.0);
+    let mut y = a.cos();
+    assert!(y.approx_eq(1.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(0.0, 1e-12));
+
+    let a = Value::new(1.0);
+    assert_eq!((&a.sin() + &a.cos()).get_ops(), "(sin(1)+cos(1))");
+}
+
+#[test]
+fn test_sigmoid() {
+    let a = Value::new(0.0);
+    let mut y = a.sigmoid();
+    assert!(y.approx_eq(0.5, 1e-12));
+    assert_eq!(y.get_ops(), "sigmoid(0)");
+    y.backward();
+    assert!(a.grad_approx_eq(0.25, 1e-12));
+
+    for x in [50.0, -50.0] {
+        let a = Value::new(x);
+        let mut y = a.sigmoid();
+        let s = 1.0 / (1.0 + f64::exp(-x));
+        assert!(y.data().is_finite());
+        assert!(y.approx_eq(s, 1e-15));
+        y.backward();
+        assert!(a.grad_f64().is_finite());
+        assert!(a.grad_approx_eq(s * (1.0 - s), 1e-15));
+    }
+}
+
+#[test]
+fn test_topo_order() {
+    let a = Value::new(2.0);
+    let b = Value::new(3.0);
+    let c = &a * &b;
+    let d = &c + &a;
+    let order = d.topo_order();
+    assert_eq!(order.len(), 4);
+    let position = |v: &Value| order.iter().position(|n| n.ptr_eq(v)).unwrap();
+    assert!(position(&a) < position(&c));
+    assert!(position(&b) < position(&c));
+    assert!(position(&c) < position(&d));
+    assert_eq!(position(&d), 3);
+}
+
+#[test]
+fn test_elu() {
+    let a = Value::new(2.0);
+    let mut y = a.elu(1.0);
+    assert!(y.approx_eq(2.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(1.0, 1e-12));
+
+    let a = Value::new(-1.0);
+    let mut y = a.elu(1.0);
+    assert!(y.approx_eq(f64::exp(-1.0) - 1.0, 1e-15));
+    y.backward();
+    assert!(a.grad_approx_eq(f64::exp(-1.0), 1e-15));
+    assert_eq!(y.get_ops(), "elu(-1)");
+}
+
+#[test]
+fn test_softplus() {
+    let a = Value::new(0.0);
+    let mut y = a.softplus();
+    assert!(y.approx_eq(2.0_f64.ln(), 1e-15));
+    y.backward();
+    assert!(a.grad_approx_eq(0.5, 1e-12));
+
+    let a = Value::new(1000.0);
+    let mut y = a.softplus();
+    assert!(y.approx_eq(1000.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(1.0, 1e-12));
+    assert!(Value::new(-1000.0).softplus().approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_no_grad_leaf() {
+    let a = Value::new(2.0);
+    let c = Value::no_grad_leaf(3.0);
+    let mut y = &a * &c;
+    assert!(y.approx_eq(6.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(3.0, 1e-12));
+    assert!(c.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_backward_accumulate() {
+    let w = Value::new(2.0);
+    let x1 = Value::new(3.0);
+    let x2 = Value::new(-1.5);
+
+    let mut loss1 = (&w * &x1).tanh();
+    let mut loss2 = (&w * &x2).pow(2.0);
+    loss1.backward();
+    let grad1 = w.grad_f64();
+    w.0.borrow_mut().grad = 0.0;
+    loss2.backward();
+    let grad2 = w.grad_f64();
+    w.0.borrow_mut().grad = 0.0;
+
+    let mut loss1 = (&w * &x1).tanh();
+    let mut loss2 = (&w * &x2).pow(2.0);
+    loss1.backward_accumulate();
+    loss2.backward_accumulate();
+    assert!(w.grad_approx_eq(grad1 + grad2, 1e-12));
+}
+
+#[test]
+fn test_powi() {
+    for x in [-2.0, 0.5, 3.0] {
+        let a = Value::new(x);
+        let mut y = a.powi(3);
+        y.backward();
+        let b = Value::new(x);
+        let mut z = b.pow(3.0);
+        z.backward();
+        assert!(y.approx_eq(z.data(), 1e-12));
+        assert!(a.grad_approx_eq(b.grad_f64(), 1e-12));
+    }
+    assert_eq!(Value::new(2.0).powi(3).get_ops(), "(2^3)");
+}
+
+#[test]
+fn test_dot() {
+    let weights = Value::vec_from(&[1.0, -2.0, 3.0, 0.5, 4.0]);
+    let inputs = Value::vec_from(&[2.0, 1.0, -1.0, 4.0, 0.25]);
+    let mut tree = Value::dot(&weights, &inputs);
+    tree.backward();
+    let tree_grads: Vec<f64> = weights
+        .iter()
+        .chain(inputs.iter())
+        .map(|v| v.grad_f64())
+        .collect();
+    for v in weights.iter().chain(inputs.iter()) {
+        v.0.borrow_mut().grad = 0.0;
+    }
+
+    let mut fold = Value::new(0.0);
+    for (w, x) in weights.iter().zip(inputs.iter()) {
+        fold = &fold + &(w * x);
+    }
+    fold.backward();
+    let fold_grads: Vec<f64> = weights
+        .iter()
+        .chain(inputs.iter())
+        .map(|v| v.grad_f64())
+        .collect();
+
+    assert!(tree.approx_eq(fold.data(), 1e-12));
+    assert_eq!(tree_grads, fold_grads);
+    // 5 products reduce in 3 levels
+    assert_eq!(
+        tree.get_ops(),
+        "((((1*2)+(-2*1))+((3*-1)+(0.5*4)))+(4*0.25))"
+    );
+}
+
+#[test]
+fn test_named() {
+    let w = Value::named(0.5, "w1");
+    let x = Value::new(2.0);
+    let y = &w * &x;
+    assert_eq!(y.get_ops(), "(w1*2)");
+    assert!(y.to_dot().contains("label=\"w1\\ndata=0.5"));
+}
+
+#[test]
+fn test_swish() {
+    let a = Value::new(0.0);
+    let mut y = a.swish();
+    assert!(y.approx_eq(0.0, 1e-12));
+    assert_eq!(y.get_ops(), "swish(0)");
+    y.backward();
+    assert!(a.grad_approx_eq(0.5, 1e-12));
+
+    let a = Value::new(1.5);
+    let mut y = a.swish();
+    y.backward();
+    let b = Value::new(1.5);
+    let mut z = &b * &b.sigmoid();
+    z.backward();
+    assert!(y.approx_eq(z.data(), 1e-15));
+    assert!(a.grad_approx_eq(b.grad_f64(), 1e-15));
+}
+
+#[test]
+fn test_accessors() {
+    let a = Value::new(2.0);
+    let b = Value::new(3.0);
+    let c = &a * &b;
+    assert_eq!(c.operator(), Operator::Mul);
+    assert_eq!(a.operator(), Operator::None);
+    let prev = c.prev();
+    assert_eq!(prev.len(), 2);
+    assert!(prev[0].ptr_eq(&a) && prev[1].ptr_eq(&b));
+    assert!(a.prev().is_empty());
+
+    c.set_grad_value(4.0);
+    assert!(c.grad_approx_eq(4.0, 1e-12));
+    assert!(a.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_backward_seeds_once() {
+    let a = Value::new(2.0);
+    let mut loss = (&a * &a).tanh();
+    loss.backward();
+    assert!(loss.grad_approx_eq(1.0, 1e-12));
+    assert!(a.grad_approx_eq(4.0 * (1.0 - 4.0_f64.tanh().powi(2)), 1e-12));
+
+    // a bare leaf as the output
+    let mut leaf = Value::new(5.0);
+    leaf.backward();
+    assert!(leaf.grad_approx_eq(1.0, 1e-12));
+}
+
+#[test]
+fn test_zeros_ones() {
+    let zeros = Value::zeros(3);
+    assert_eq!(values_to_f64(&zeros), vec![0.0; 3]);
+    assert_eq!(values_to_f64(&Value::ones(4)), vec![1.0; 4]);
+    assert!(Value::ones(0).is_empty());
+    // every element is its own leaf
+    assert!(!zeros[0].ptr_eq(&zeros[1]));
+}
+
+#[test]
+fn test_update_data() {
+    let a = Value::new(1.5);
+    let alias = a.clone();
+    a.update_data(-0.5);
+    assert!(a.approx_eq(1.0, 1e-12));
+    assert!(alias.approx_eq(1.0, 1e-12));
+}
+
+#[test]
+fn test_sum_mean() {
+    let values = Value::vec_from(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+    let mut total = sum(&values);
+    assert!(total.approx_eq(15.0, 1e-12));
+    total.backward();
+    for v in values.iter() {
+        assert!(v.grad_approx_eq(1.0, 1e-12));
+    }
+
+    let values = Value::vec_from(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+    let mut avg = mean(&values);
+    assert!(avg.approx_eq(3.0, 1e-12));
+    avg.backward();
+    for v in values.iter() {
+        assert!(v.grad_approx_eq(0.2, 1e-12));
+    }
+
+    assert!(sum(&[]).approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_recip() {
+    let x = Value::new(2.0);
+    let mut y = x.recip();
+    assert!(y.approx_eq(0.5, 1e-12));
+    assert_eq!(y.operator(), Operator::Recip);
+    assert_eq!(y.get_ops(), "recip(2)");
+    y.backward();
+    assert!(x.grad_approx_eq(-0.25, 1e-12));
+}
+
+#[test]
+fn test_default() {
+    let value = Value::default();
+    assert!(value.approx_eq(0.0, 1e-12));
+    assert!(value.grad_approx_eq(0.0, 1e-12));
+    assert!(value.prev().is_empty());
+    assert_eq!(mlp::MLP::default().num_parameters(), 0);
+}
+
+#[test]
+fn test_get_ops_unary() {
+    let x = Value::new(0.5);
+    assert_eq!(x.tanh().get_ops(), "tanh(0.5)");
+    assert_eq!(x.exp().get_ops(), "exp(0.5)");
+    let a = Value::named(1.0, "a");
+    assert_eq!((&a + &x.tanh()).exp().get_ops(), "exp((a+tanh(0.5)))");
+}
+
+#[test]
+fn test_local_grad() {
+    let a = Value::new(3.0);
+    let b = Value::new(-2.0);
+    assert_eq!((&a * &b).local_grad(), vec![-2.0, 3.0]);
+    assert_eq!((&a + &b).local_grad(), vec![1.0, 1.0]);
+    let t = Value::new(0.5).tanh();
+    assert_eq!(t.local_grad(), vec![1.0 - t.data().powi(2)]);
+    assert!(a.local_grad().is_empty());
+}
+
+#[test]
+fn test_gelu() {
+    for x in [-2.0, -0.5, 0.0, 0.7, 3.0] {
+        let expected = 0.5
+            * x
+            * (1.0 + ((2.0 / std::f64::consts::PI).sqrt() * (x + 0.044715 * x * x * x)).tanh());
+        assert!((Value::new(x).gelu().data() - expected).abs() < 1e-12);
+    }
+    let inputs = Value::vec_from(&[-1.3, 0.2, 2.5]);
+    let f = |xs: &[Value]| xs.iter().map(|x| x.gelu()).sum();
+    assert!(gradcheck::grad_check(f, &inputs, 1e-5) < 1e-6);
+}
+
+#[test]
+fn test_map_data() {
+    let x = Value::new(3.0);
+    let y = &x * 2.0;
+    let mut scaled = y.map_data(|d| (d - 1.0) / 5.0);
+    assert!(scaled.approx_eq(1.0, 1e-12));
+    assert_eq!(scaled.operator(), Operator::None);
+    assert!(scaled.prev().is_empty());
+    scaled.backward();
+    assert!(x.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_backward_with() {
+    let build = || {
+        let a = Value::new(1.5);
+        let b = Value::new(-0.5);
+        let out = (&(&a * &b) + &a.tanh()).exp();
+        (a, b, out)
+    };
+    let (a1, b1, mut out1) = build();
+    out1.backward();
+    let (a2, b2, mut out2) = build();
+    out2.backward_with(2.0);
+    assert!(out2.grad_approx_eq(2.0, 1e-12));
+    assert!(a2.grad_approx_eq(2.0 * a1.grad_f64(), 1e-12));
+    assert!(b2.grad_approx_eq(2.0 * b1.grad_f64(), 1e-12));
+}
+
+#[test]
+fn test_is_leaf() {
+    let a = Value::new(1.0);
+    let b = Value::new(2.0);
+    let sum = &a + &b;
+    assert!(a.is_leaf());
+    assert!(!sum.is_leaf());
+    assert!(sum.detach().is_leaf());
+    assert!(with_no_grad(|| &a + &b).is_leaf());
+}
+
+#[test]
+#[should_panic(expected = "cycle detected in computation graph")]
+fn test_cycle_detected() {
+    let a = Value::new(1.0);
+    let mut b = &a + 1.0;
+    // only reachable from inside the crate now that `prev` is private
+    a.0.borrow_mut().prev.push(b.clone());
+    b.backward();
+}
+
+#[test]
+fn test_shared_node_is_not_a_cycle() {
+    let a = Value::new(2.0);
+    let b = &a * &a;
+    let mut c = &(&b + &a) * &b;
+    c.backward();
+    // c = a^4 + a^3
+    assert!(a.grad_approx_eq(4.0 * 8.0 + 3.0 * 4.0, 1e-12));
+}
+
+#[test]
+fn test_clone_detached_graph() {
+    let a = Value::named(2.0, "a");
+    let b = &a * &a;
+    let out = &(&b + &a) * &b;
+    let mut copy = out.clone_detached_graph();
+    assert!(copy.approx_eq(out.data(), 1e-12));
+    assert_eq!(copy.get_ops(), out.get_ops());
+    assert!(!copy.ptr_eq(&out));
+    // the shared `b` and `a` are copied once each: out, b+a, b, a
+    assert_eq!(copy.topo_order().len(), out.topo_order().len());
+
+    let copied_a = copy.topo_order()[0].clone();
+    assert!(copied_a.is_leaf());
+    copied_a.update_data(1.0);
+    assert!(a.approx_eq(2.0, 1e-12));
+    copy.backward();
+    assert!(a.grad_approx_eq(0.0, 1e-12));
+    assert!(copied_a.grad_f64() != 0.0);
+}
+
+#[test]
+fn test_approx_eq() {
+    let a = Value::new(0.1);
+    let mut sum = &(&a + 0.2) * 1.0;
+    assert_ne!(sum.data(), 0.3);
+    assert!(sum.approx_eq(0.3, 1e-12));
+    assert!(!sum.approx_eq(0.31, 1e-3));
+    sum.backward();
+    assert!(a.grad_approx_eq(1.0, 0.0));
+    assert!(!a.grad_approx_eq(1.1, 0.05));
+}
+
+#[test]
+fn test_last_backward_op_count() {
+    // every level feeds the next one twice: 2^30 paths through 31 nodes
+    let x = Value::new(1.0);
+    let mut top = x.clone();
+    for _ in 0..30 {
+        top = &top + &top;
+    }
+    top.backward();
+    assert_eq!(Value::last_backward_op_count(), 31);
+    assert!(x.grad_approx_eq(2.0_f64.powi(30), 1e-12));
+
+    // constants that don't take gradient aren't counted
+    let mut y = &Value::no_grad_leaf(2.0) * &x;
+    y.backward();
+    assert_eq!(Value::last_backward_op_count(), 2);
+}
+
+#[test]
+fn test_equality() {
+    let a = Value::new(2.0);
+    let b = Value::new(1.0);
+    let sum = &a + &b;
+    let product = &Value::new(3.0) * &Value::new(1.0);
+    // equal data, different histories
+    assert_eq!(sum, product);
+    assert_eq!(sum.partial_cmp(&product), Some(Ordering::Equal));
+    assert!(!sum.structural_eq(&product));
+    assert!(sum.structural_eq(&(&Value::new(2.0) + &Value::new(1.0))));
+    assert!(sum.structural_eq(&sum.clone_detached_graph()));
+    assert_ne!(a, b);
+
+    // 2^1000 paths, but each pair of nodes is compared once
+    let deep = |x: f64| {
+        let mut top = Value::new(x);
+        for _ in 0..1000 {
+            top = &top + &top;
+        }
+        top
+    };
+    let (left, right) = (deep(0.0), deep(0.0));
+    assert!(left.structural_eq(&right));
+    assert!(!left.structural_eq(&deep(1.0)));
+}
+
+#[test]
+fn test_is_finite() {
+    assert!(Value::new(1.0).is_finite());
+    assert!(!Value::new(0.0).log().is_finite());
+    assert!(!Value::new(-1.0).sqrt().is_finite());
+}
+
+#[test]
+fn test_pow_negative_base() {
+    let a = Value::new(-2.0);
+    let mut y = a.pow(3.0);
+    assert!(y.approx_eq(-8.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(12.0, 1e-12)); // 3 * (-2)^2
+
+    let b = Value::new(-2.0);
+    let mut y = b.pow(0.5);
+    assert!(y.data().is_nan());
+    y.backward();
+    assert!(b.grad_f64().is_nan());
 }
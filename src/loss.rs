@@ -0,0 +1,234 @@
+use crate::{Operator, Value};
+
+/// Mean of the squared differences between `pred` and `target`.
+pub fn mse(pred: &[Value], target: &[Value]) -> Value {
+    assert_eq!(pred.len(), target.len());
+    let total: Value = pred
+        .iter()
+        .zip(target.iter())
+        .map(|(p, t)| (p - t).powi(2))
+        .sum();
+    &total / pred.len() as f64
+}
+
+/// Binary cross-entropy, `-mean(t * log(p) + (1 - t) * log(1 - p))`.
+/// Predictions are expected to lie strictly inside `(0, 1)`.
+pub fn bce(pred: &[Value], target: &[Value]) -> Value {
+    assert_eq!(pred.len(), target.len());
+    let total: Value = pred
+        .iter()
+        .zip(target.iter())
+        .map(|(p, t)| &(t * &p.log()) + &(&(1.0 - t) * &(1.0 - p).log()))
+        .sum();
+    &total / -(pred.len() as f64)
+}
+
+/// `lambda * sum(w^2)` over `params`, to be added to the data loss.
+pub fn l2_penalty(params: &[Value], lambda: f64) -> Value {
+    let total: Value = params.iter().map(|w| w.powi(2)).sum();
+    &total * lambda
+}
+
+/// Huber (smooth L1) loss, averaged over the batch: `0.5 * r^2` for
+/// residuals with `|r| <= delta` and `delta * (|r| - 0.5 * delta)` beyond.
+/// Both pieces agree in value and slope at `|r| == delta`.
+pub fn huber(pred: &[Value], target: &[Value], delta: f64) -> Value {
+    assert_eq!(pred.len(), target.len());
+    let total: Value = pred
+        .iter()
+        .zip(target.iter())
+        .map(|(p, t)| {
+            let residual = p - t;
+            if residual.data().abs() <= delta {
+                &residual.powi(2) * 0.5
+            } else {
+                &(&residual.abs() - 0.5 * delta) * delta
+            }
+        })
+        .sum();
+    &total / pred.len() as f64
+}
+
+/// Averages per-example losses over a batch, so each example's loss receives
+/// gradient `1 / n`. The sum is a balanced tree, as in [`crate::mean`].
+pub fn batch_mean(losses: &[Value]) -> Value {
+    crate::mean(losses)
+}
+
+/// Negative log-likelihood of class `target` given log-probabilities, as
+/// produced by [`crate::functional::log_softmax`].
+pub fn nll_loss(log_probs: &[Value], target: usize) -> Value {
+    assert!(
+        target < log_probs.len(),
+        "target {} out of range for {} log-probabilities",
+        target,
+        log_probs.len()
+    );
+    -&log_probs[target]
+}
+
+/// Softmax cross-entropy of class `target`, `logsumexp(logits) -
+/// logits[target]`, as a single node. Its backward writes `softmax - onehot`
+/// straight into the logits instead of going through separate softmax and
+/// log nodes.
+pub fn cross_entropy(logits: &[Value], target: usize) -> Value {
+    assert!(
+        target < logits.len(),
+        "target {} out of range for {} logits",
+        target,
+        logits.len()
+    );
+    let data: Vec<f64> = crate::values_to_f64(logits);
+    let max = data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let log_sum_exp = max + data.iter().map(|x| (x - max).exp()).sum::<f64>().ln();
+    Value::from_op(
+        log_sum_exp - data[target],
+        Operator::CrossEntropy(target),
+        logits.to_vec(),
+    )
+}
+
+/// KL divergence `sum(p * log(p / q))` of `q` from `p`. Terms with
+/// `p_i == 0` contribute nothing, following `0 * log(0) = 0`. Gradient flows
+/// into both distributions; pass `p` through [`Value::detach`] to train only
+/// `q` against a fixed target.
+pub fn kl_divergence(p: &[Value], q: &[Value]) -> Value {
+    assert_eq!(p.len(), q.len());
+    let terms: Vec<Value> = p
+        .iter()
+        .zip(q.iter())
+        .filter(|(p, _)| p.data() != 0.0)
+        .map(|(p, q)| p * &(p / q).log())
+        .collect();
+    crate::sum(&terms)
+}
+
+#[test]
+fn test_mse() {
+    let pred = vec![Value::new(0.8), Value::new(0.4)];
+    let target = vec![Value::new(1.0), Value::new(0.0)];
+    let mut loss = mse(&pred, &target);
+    assert!(loss.approx_eq(0.1, 1e-12));
+    loss.backward();
+    // 2 * (p - t) / n
+    assert!(pred[0].grad_approx_eq(-0.2, 1e-12));
+    assert!(pred[1].grad_approx_eq(0.4, 1e-12));
+}
+
+#[test]
+fn test_bce() {
+    let pred = vec![Value::new(0.8), Value::new(0.4)];
+    let target = vec![Value::new(1.0), Value::new(0.0)];
+    let mut loss = bce(&pred, &target);
+    let expected = -(0.8_f64.ln() + 0.6_f64.ln()) / 2.0;
+    assert!(loss.approx_eq(expected, 1e-12));
+    loss.backward();
+    // -(t / p - (1 - t) / (1 - p)) / n
+    assert!(pred[0].grad_approx_eq(-0.625, 1e-12));
+    assert!(pred[1].grad_approx_eq(1.0 / 1.2, 1e-12));
+}
+
+#[test]
+fn test_l2_penalty() {
+    let params = vec![Value::new(0.5), Value::new(-2.0), Value::new(3.0)];
+    let mut penalty = l2_penalty(&params, 0.1);
+    assert!(penalty.approx_eq(0.1 * 13.25, 1e-12));
+    penalty.backward();
+    for w in params.iter() {
+        assert!(w.grad_approx_eq(2.0 * 0.1 * w.data(), 1e-12));
+    }
+}
+
+#[test]
+fn test_huber() {
+    // quadratic regime: gradient is r / n
+    let pred = vec![Value::new(0.5), Value::new(-0.25)];
+    let target = vec![Value::new(0.0), Value::new(0.0)];
+    let mut loss = huber(&pred, &target, 1.0);
+    assert!(loss.approx_eq((0.125 + 0.03125) / 2.0, 1e-12));
+    loss.backward();
+    assert!(pred[0].grad_approx_eq(0.25, 1e-12));
+    assert!(pred[1].grad_approx_eq(-0.125, 1e-12));
+
+    // linear regime: gradient is delta * sign(r) / n
+    let pred = vec![Value::new(3.0), Value::new(-5.0)];
+    let mut loss = huber(&pred, &target, 1.0);
+    assert!(loss.approx_eq((2.5 + 4.5) / 2.0, 1e-12));
+    loss.backward();
+    assert!(pred[0].grad_approx_eq(0.5, 1e-12));
+    assert!(pred[1].grad_approx_eq(-0.5, 1e-12));
+
+    // at the boundary both pieces give the same gradient
+    let pred = vec![Value::new(1.0)];
+    let mut loss = huber(&pred, &target[..1], 1.0);
+    loss.backward();
+    assert!(pred[0].grad_approx_eq(1.0, 1e-12));
+}
+
+#[test]
+fn test_nll_loss_log_softmax() {
+    use crate::functional::{log_softmax, softmax};
+
+    let logits = Value::vec_from(&[1.0, -0.5, 0.25]);
+    let mut loss = nll_loss(&log_softmax(&logits), 0);
+    let probs = softmax(&logits);
+    assert!(loss.approx_eq(-probs[0].data().ln(), 1e-12));
+    loss.backward();
+    // d/dlogits = softmax - onehot
+    let onehot = [1.0, 0.0, 0.0];
+    for i in 0..3 {
+        let expected = probs[i].data() - onehot[i];
+        assert!(logits[i].grad_approx_eq(expected, 1e-12));
+    }
+}
+
+#[test]
+fn test_kl_divergence() {
+    let p = Value::vec_from(&[0.2, 0.3, 0.5]);
+    let same = Value::vec_from(&[0.2, 0.3, 0.5]);
+    assert!(kl_divergence(&p, &same).data().abs() < 1e-12);
+
+    let p = Value::vec_from(&[0.25, 0.75, 0.0]);
+    let q = Value::vec_from(&[0.5, 0.25, 0.25]);
+    let mut loss = kl_divergence(&p, &q);
+    let expected = 0.25 * 0.5_f64.ln() + 0.75 * 3.0_f64.ln();
+    assert!(loss.approx_eq(expected, 1e-12));
+    loss.backward();
+    // d/dq_i = -p_i / q_i and d/dp_i = log(p_i / q_i) + 1
+    assert!(q[0].grad_approx_eq(-0.5, 1e-12));
+    assert!(q[1].grad_approx_eq(-3.0, 1e-12));
+    assert!(q[2].grad_approx_eq(0.0, 1e-12));
+    assert!(p[1].grad_approx_eq(3.0_f64.ln() + 1.0, 1e-12));
+}
+
+#[test]
+fn test_cross_entropy() {
+    use crate::functional::softmax;
+
+    let logits = Value::vec_from(&[2.0, 1.0, 0.1]);
+    let mut loss = cross_entropy(&logits, 1);
+    let probs = softmax(&logits);
+    assert!(loss.approx_eq(-probs[1].data().ln(), 1e-12));
+    assert_eq!(loss.topo_order().len(), 4);
+    loss.backward();
+    let onehot = [0.0, 1.0, 0.0];
+    for i in 0..3 {
+        let expected = probs[i].data() - onehot[i];
+        assert!(logits[i].grad_approx_eq(expected, 1e-12));
+    }
+
+    // stable for logits that would overflow exp
+    let loss = cross_entropy(&Value::vec_from(&[1000.0, 0.0]), 1);
+    assert!(loss.approx_eq(1000.0, 1e-9));
+}
+
+#[test]
+fn test_batch_mean() {
+    let losses = Value::vec_from(&[0.5, 1.5, 2.0, 4.0]);
+    let mut loss = batch_mean(&losses);
+    assert!(loss.approx_eq(2.0, 1e-12));
+    loss.backward();
+    for l in losses.iter() {
+        assert!(l.grad_approx_eq(0.25, 1e-12));
+    }
+}
@@ -0,0 +1,53 @@
+use crate::Value;
+
+/// Fraction of examples whose highest-scoring output matches the target
+/// class index. Ties go to the lowest index. Only reads data; the graph is
+/// left untouched. Panics on an empty batch.
+pub fn accuracy(preds: &[Vec<Value>], targets: &[usize]) -> f64 {
+    assert_eq!(preds.len(), targets.len());
+    assert!(!preds.is_empty(), "accuracy needs at least one example");
+    let correct = preds
+        .iter()
+        .zip(targets.iter())
+        .filter(|(pred, &target)| {
+            let argmax = pred
+                .iter()
+                .enumerate()
+                .reduce(|best, next| {
+                    if next.1.data().total_cmp(&best.1.data()).is_gt() {
+                        next
+                    } else {
+                        best
+                    }
+                })
+                .map(|(i, _)| i);
+            argmax == Some(target)
+        })
+        .count();
+    correct as f64 / preds.len() as f64
+}
+
+#[test]
+fn test_accuracy() {
+    let preds = vec![
+        Value::vec_from(&[0.1, 0.7, 0.2]),
+        Value::vec_from(&[0.8, 0.1, 0.1]),
+        Value::vec_from(&[0.3, 0.3, 0.4]),
+        Value::vec_from(&[0.5, 0.2, 0.3]),
+    ];
+    assert_eq!(accuracy(&preds, &[1, 0, 2, 1]), 0.75);
+    assert_eq!(accuracy(&preds, &[0, 1, 0, 2]), 0.0);
+}
+
+#[test]
+fn test_accuracy_ties() {
+    let preds = vec![Value::vec_from(&[0.4, 0.4, 0.2])];
+    assert_eq!(accuracy(&preds, &[0]), 1.0);
+    assert_eq!(accuracy(&preds, &[1]), 0.0);
+}
+
+#[test]
+#[should_panic(expected = "at least one example")]
+fn test_accuracy_empty() {
+    accuracy(&[], &[]);
+}
@@ -1,5 +1,46 @@
+use crate::optim::Optimizer;
 use crate::Value;
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::{fs, io, path::Path};
+
+/// Weight initialization scheme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Init {
+    /// Weights and bias uniform in `-1..1`, regardless of fan-in.
+    Uniform,
+    /// Glorot uniform, `±sqrt(6 / (n_in + n_out))`, with a zero bias. Suited
+    /// to tanh/sigmoid.
+    Xavier,
+    /// He normal, `N(0, sqrt(2 / n_in))`, with a zero bias. Suited to ReLU.
+    He,
+}
+
+pub fn relu_fn(x: Value) -> Value {
+    x.relu()
+}
+
+pub fn tanh_fn(x: Value) -> Value {
+    x.tanh()
+}
+
+pub fn sigmoid_fn(x: Value) -> Value {
+    x.sigmoid()
+}
+
+pub fn identity_fn(x: Value) -> Value {
+    x
+}
+
+// Box-Muller, to avoid pulling in rand_distr for a single distribution.
+fn sample_normal(rng: &mut impl Rng, std: f64) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>(); // in (0, 1], keeps ln finite
+    let u2: f64 = rng.gen();
+    std * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
 
 pub struct Neuron {
     weights: Vec<Value>,
@@ -12,22 +53,71 @@ impl Neuron {
     }
 
     pub fn new_random(n_inputs: usize) -> Self {
+        Neuron::new_random_seeded(n_inputs, &mut rand::thread_rng())
+    }
+
+    /// Like [`Neuron::new_random`], drawing from `rng` so that a seeded
+    /// generator gives the same weights every run.
+    pub fn new_random_seeded(n_inputs: usize, rng: &mut impl Rng) -> Self {
         let between = Uniform::from(-1.0..1.);
-        let mut rng = rand::thread_rng();
         let weights: Vec<Value> = (0..n_inputs)
-            .map(|_| Value::new(between.sample(&mut rng)))
+            .map(|_| Value::new(between.sample(rng)))
             .collect();
-        let bias = Value::new(between.sample(&mut rng));
+        let bias = Value::new(between.sample(rng));
         Neuron { weights, bias }
     }
 
+    /// Glorot uniform weights for a neuron in a layer of `n_outputs`
+    /// neurons. The bias starts at zero.
+    pub fn new_xavier(n_inputs: usize, n_outputs: usize) -> Self {
+        Neuron::new_with_init(n_inputs, n_outputs, Init::Xavier)
+    }
+
+    /// He normal weights. The bias starts at zero.
+    pub fn new_he(n_inputs: usize) -> Self {
+        // He only looks at the fan-in, so any fan-out will do
+        Neuron::new_with_init(n_inputs, 1, Init::He)
+    }
+
+    /// Weights drawn by `init`. `n_outputs` is the fan-out of the layer the
+    /// neuron sits in and is only read by [`Init::Xavier`]. The bias is
+    /// uniform in `-1..1` for [`Init::Uniform`] and zero otherwise.
+    pub fn new_with_init(n_inputs: usize, n_outputs: usize, init: Init) -> Self {
+        Neuron::new_with_init_seeded(n_inputs, n_outputs, init, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_init_seeded(
+        n_inputs: usize,
+        n_outputs: usize,
+        init: Init,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let weights: Vec<Value> = match init {
+            Init::Uniform => return Neuron::new_random_seeded(n_inputs, rng),
+            Init::Xavier => {
+                let limit = (6.0 / (n_inputs + n_outputs) as f64).sqrt();
+                let between = Uniform::from(-limit..limit);
+                (0..n_inputs)
+                    .map(|_| Value::new(between.sample(rng)))
+                    .collect()
+            }
+            Init::He => {
+                let std = (2.0 / n_inputs as f64).sqrt();
+                (0..n_inputs)
+                    .map(|_| Value::new(sample_normal(rng, std)))
+                    .collect()
+            }
+        };
+        Neuron::new(weights, Value::new(0.0))
+    }
+
     pub fn forward(&self, inputs: Vec<Value>) -> Value {
+        self.forward_slice(&inputs)
+    }
+
+    pub fn forward_slice(&self, inputs: &[Value]) -> Value {
         assert_eq!(inputs.len(), self.weights.len());
-        let mut sum = self.bias.clone();
-        for i in 0..self.weights.len() {
-            sum = &sum + &(&self.weights[i] * &inputs[i]);
-        }
-        sum
+        &Value::dot(&self.weights, inputs) + &self.bias
     }
 
     pub fn parameters(&self) -> Vec<Value> {
@@ -35,59 +125,407 @@ impl Neuron {
         params.push(self.bias.clone());
         params
     }
+
+    /// Calls `f` on every weight and then the bias, in [`Neuron::parameters`]
+    /// order, without collecting them.
+    pub fn apply_to_params(&self, f: impl Fn(&Value)) {
+        self.weights.iter().for_each(&f);
+        f(&self.bias);
+    }
 }
 
 pub struct Layer {
     neurons: Vec<Neuron>,
     func: fn(Value) -> Value,
+    dropout: f64,
+    training: bool,
+    frozen: bool,
 }
 
 impl Layer {
     pub fn new(neurons: Vec<Neuron>, func: fn(Value) -> Value) -> Self {
-        Layer { neurons, func }
+        Layer {
+            neurons,
+            func,
+            dropout: 0.0,
+            training: true,
+            frozen: false,
+        }
     }
 
     pub fn new_random(n_inputs: usize, n_neurons: usize, func: fn(Value) -> Value) -> Self {
+        Layer::new_random_seeded(n_inputs, n_neurons, func, &mut rand::thread_rng())
+    }
+
+    pub fn new_random_seeded(
+        n_inputs: usize,
+        n_neurons: usize,
+        func: fn(Value) -> Value,
+        rng: &mut impl Rng,
+    ) -> Self {
+        Layer::new_with_init_seeded(n_inputs, n_neurons, func, Init::Uniform, rng)
+    }
+
+    pub fn new_with_init(
+        n_inputs: usize,
+        n_neurons: usize,
+        func: fn(Value) -> Value,
+        init: Init,
+    ) -> Self {
+        Layer::new_with_init_seeded(n_inputs, n_neurons, func, init, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_init_seeded(
+        n_inputs: usize,
+        n_neurons: usize,
+        func: fn(Value) -> Value,
+        init: Init,
+        rng: &mut impl Rng,
+    ) -> Self {
         let neurons: Vec<Neuron> = (0..n_neurons)
-            .map(|_| Neuron::new_random(n_inputs))
+            .map(|_| Neuron::new_with_init_seeded(n_inputs, n_neurons, init, rng))
             .collect();
-        Layer { neurons, func }
+        Layer::new(neurons, func)
     }
 
     pub fn forward(&self, inputs: Vec<Value>) -> Vec<Value> {
-        self.neurons
+        self.forward_slice(&inputs)
+    }
+
+    /// Every neuron borrows the same `inputs`; only the individual `Value`s
+    /// it consumes are cloned into the graph.
+    pub fn forward_slice(&self, inputs: &[Value]) -> Vec<Value> {
+        self.forward_slice_seeded(inputs, &mut rand::thread_rng())
+    }
+
+    /// Like [`Layer::forward_slice`], drawing the dropout mask from `rng` so
+    /// it can be reproduced.
+    pub fn forward_slice_seeded(&self, inputs: &[Value], rng: &mut impl Rng) -> Vec<Value> {
+        self.forward_mode(inputs, self.training, rng)
+    }
+
+    fn forward_mode(&self, inputs: &[Value], training: bool, rng: &mut impl Rng) -> Vec<Value> {
+        let outputs = self
+            .neurons
             .iter()
-            .map(|neuron| (self.func)(neuron.forward(inputs.clone())))
+            .map(|neuron| (self.func)(neuron.forward_slice(inputs)));
+        if !training || self.dropout == 0.0 {
+            return outputs.collect();
+        }
+        // Inverted dropout: the mask is drawn once per call, dropped units
+        // become constant zeros and survivors are scaled by 1 / (1 - p).
+        let scale = 1.0 / (1.0 - self.dropout);
+        outputs
+            .map(|output| {
+                if rng.gen::<f64>() < self.dropout {
+                    Value::new(0.0)
+                } else {
+                    &output * scale
+                }
+            })
             .collect()
     }
 
+    /// Drops each activation with probability `p` while training.
+    pub fn set_dropout(&mut self, p: f64) {
+        assert!(
+            (0.0..1.0).contains(&p),
+            "dropout must be in [0, 1), got {}",
+            p
+        );
+        self.dropout = p;
+    }
+
+    pub fn train(&mut self) {
+        self.training = true;
+    }
+
+    pub fn eval(&mut self) {
+        self.training = false;
+    }
+
+    /// Excludes this layer from [`Layer::trainable_parameters`]. Gradients
+    /// still flow through it to earlier layers.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+    }
+
     pub fn parameters(&self) -> Vec<Value> {
         self.neurons
             .iter()
             .flat_map(|neuron| neuron.parameters())
             .collect()
     }
+
+    /// The parameters, or nothing if the layer is frozen.
+    pub fn trainable_parameters(&self) -> Vec<Value> {
+        if self.frozen {
+            Vec::new()
+        } else {
+            self.parameters()
+        }
+    }
+
+    pub fn apply_to_params(&self, f: impl Fn(&Value)) {
+        for neuron in self.neurons.iter() {
+            neuron.apply_to_params(&f);
+        }
+    }
+}
+
+/// A layer with a skip connection, `y = layer(x) + x`. Gradient reaches `x`
+/// both through the layer and directly.
+pub struct ResidualLayer {
+    layer: Layer,
+}
+
+impl ResidualLayer {
+    /// `layer` must have as many neurons as inputs.
+    pub fn new(layer: Layer) -> Self {
+        let inputs = layer.neurons.first().map_or(0, |n| n.weights.len());
+        assert_eq!(
+            inputs,
+            layer.neurons.len(),
+            "a residual layer needs matching input and output sizes"
+        );
+        ResidualLayer { layer }
+    }
+
+    pub fn forward(&self, inputs: Vec<Value>) -> Vec<Value> {
+        self.forward_slice(&inputs)
+    }
+
+    pub fn forward_slice(&self, inputs: &[Value]) -> Vec<Value> {
+        self.layer
+            .forward_slice(inputs)
+            .iter()
+            .zip(inputs.iter())
+            .map(|(output, input)| output + input)
+            .collect()
+    }
+
+    pub fn parameters(&self) -> Vec<Value> {
+        self.layer.parameters()
+    }
+}
+
+/// Summary statistics over a set of parameter values or gradients, as
+/// returned by [`MLP::param_stats`] and [`MLP::grad_stats`]. `std` is the
+/// population standard deviation. Everything is `0.0` when there are no
+/// values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std: f64,
+}
+
+impl ParamStats {
+    fn from_data(data: &[f64]) -> Self {
+        if data.is_empty() {
+            return ParamStats {
+                min: 0.0,
+                max: 0.0,
+                mean: 0.0,
+                std: 0.0,
+            };
+        }
+        let n = data.len() as f64;
+        let mean = data.iter().sum::<f64>() / n;
+        let var = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        ParamStats {
+            min: data.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: data.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            std: var.sqrt(),
+        }
+    }
 }
 
 pub struct MLP {
     layers: Vec<Layer>,
 }
 
+impl Default for MLP {
+    fn default() -> Self {
+        MLP::new()
+    }
+}
+
 impl MLP {
     pub fn new() -> Self {
         MLP { layers: Vec::new() }
     }
 
+    /// Builds layers of `sizes[1..]` neurons, each fed by the previous size,
+    /// with `funcs[i]` applied after layer `i`.
+    pub fn from_sizes(sizes: &[usize], funcs: &[fn(Value) -> Value]) -> Self {
+        MLP::from_sizes_seeded(sizes, funcs, &mut rand::thread_rng())
+    }
+
+    pub fn from_sizes_seeded(
+        sizes: &[usize],
+        funcs: &[fn(Value) -> Value],
+        rng: &mut impl Rng,
+    ) -> Self {
+        assert!(
+            sizes.len() >= 2,
+            "MLP::from_sizes needs at least an input and an output size, got {:?}",
+            sizes
+        );
+        assert_eq!(
+            funcs.len(),
+            sizes.len() - 1,
+            "MLP::from_sizes needs one activation per layer: {} sizes make {} layers but {} functions were given",
+            sizes.len(),
+            sizes.len() - 1,
+            funcs.len()
+        );
+        let mut mlp = MLP::new();
+        for (size, func) in sizes.windows(2).zip(funcs.iter()) {
+            mlp.add_layer_with_init_seeded(size[0], size[1], *func, Init::Uniform, rng);
+        }
+        mlp
+    }
+
     pub fn add_layer(&mut self, inp: usize, out: usize, func: fn(Value) -> Value) {
-        let n_inputs = self.layers.last().map_or(inp, |layer| layer.neurons.len());
-        let layer = Layer::new_random(n_inputs, out, func);
+        self.add_layer_with_init(inp, out, func, Init::Uniform);
+    }
+
+    pub fn add_layer_with_init(
+        &mut self,
+        inp: usize,
+        out: usize,
+        func: fn(Value) -> Value,
+        init: Init,
+    ) {
+        self.add_layer_with_init_seeded(inp, out, func, init, &mut rand::thread_rng());
+    }
+
+    pub fn add_layer_with_init_seeded(
+        &mut self,
+        inp: usize,
+        out: usize,
+        func: fn(Value) -> Value,
+        init: Init,
+        rng: &mut impl Rng,
+    ) {
+        if let Some(last) = self.layers.last() {
+            assert_eq!(
+                inp,
+                last.neurons.len(),
+                "layer input size must match the previous layer's {} outputs",
+                last.neurons.len()
+            );
+        }
+        let layer = Layer::new_with_init_seeded(inp, out, func, init, rng);
         self.layers.push(layer);
     }
 
+    /// Sets the dropout probability applied after layer `layer`.
+    pub fn set_dropout(&mut self, layer: usize, p: f64) {
+        self.layers[layer].set_dropout(p);
+    }
+
+    /// Enables dropout in every layer.
+    pub fn train(&mut self) {
+        self.layers.iter_mut().for_each(Layer::train);
+    }
+
+    /// Disables dropout in every layer.
+    pub fn eval(&mut self) {
+        self.layers.iter_mut().for_each(Layer::eval);
+    }
+
+    /// Freezes layer `layer`; see [`Layer::freeze`].
+    pub fn freeze(&mut self, layer: usize) {
+        self.layers[layer].freeze();
+    }
+
+    pub fn unfreeze(&mut self, layer: usize) {
+        self.layers[layer].unfreeze();
+    }
+
     pub fn forward(&self, inputs: Vec<Value>) -> Vec<Value> {
-        self.layers
+        self.forward_slice(&inputs)
+    }
+
+    pub fn forward_slice(&self, inputs: &[Value]) -> Vec<Value> {
+        self.forward_slice_seeded(inputs, &mut rand::thread_rng())
+    }
+
+    /// Like [`MLP::forward_slice`], drawing every dropout mask from `rng`.
+    pub fn forward_slice_seeded(&self, inputs: &[Value], rng: &mut impl Rng) -> Vec<Value> {
+        match self.layers.split_first() {
+            Some((first, rest)) => rest
+                .iter()
+                .fold(first.forward_slice_seeded(inputs, rng), |inputs, layer| {
+                    layer.forward_slice_seeded(&inputs, rng)
+                }),
+            None => inputs.to_vec(),
+        }
+    }
+
+    /// Like [`MLP::forward`], also returning the output of every layer in
+    /// order, the last being the final output. These are the nodes of the
+    /// forward graph themselves, so gradients can be read from or
+    /// backpropagated through them.
+    pub fn forward_with_activations(&self, inputs: Vec<Value>) -> (Vec<Value>, Vec<Vec<Value>>) {
+        let mut activations: Vec<Vec<Value>> = Vec::with_capacity(self.layers.len());
+        for layer in self.layers.iter() {
+            let layer_inputs = activations.last().map_or(&inputs[..], |a| &a[..]);
+            let outputs = layer.forward_slice(layer_inputs);
+            activations.push(outputs);
+        }
+        let output = activations.last().cloned().unwrap_or(inputs);
+        (output, activations)
+    }
+
+    /// Inference on plain floats: runs the network as in eval mode, without
+    /// dropout and without building a graph, and returns the output data.
+    pub fn predict(&self, inputs: &[f64]) -> Vec<f64> {
+        crate::with_no_grad(|| {
+            let outputs = self
+                .layers
+                .iter()
+                .fold(Value::vec_from(inputs), |inputs, layer| {
+                    layer.forward_mode(&inputs, false, &mut rand::thread_rng())
+                });
+            crate::values_to_f64(&outputs)
+        })
+    }
+
+    /// One online update: builds the graph for a single example, backprops
+    /// `loss_fn(output, target)`, steps `optimizer` and zeroes its gradients.
+    /// Returns the loss before the step.
+    pub fn train_step(
+        &self,
+        input: &[f64],
+        target: &[f64],
+        optimizer: &mut impl Optimizer,
+        loss_fn: impl Fn(&[Value], &[Value]) -> Value,
+    ) -> f64 {
+        let output = self.forward(Value::vec_from(input));
+        let mut loss = loss_fn(&output, &Value::vec_from(target));
+        loss.backward();
+        optimizer.step();
+        optimizer.zero_grad();
+        loss.data()
+    }
+
+    /// Runs [`MLP::forward`] on every example. All of the resulting graphs
+    /// share this network's parameter leaves, so backpropagating a loss over
+    /// the whole batch accumulates every example's gradient before a step.
+    pub fn forward_batch(&self, batch: &[Vec<Value>]) -> Vec<Vec<Value>> {
+        batch
             .iter()
-            .fold(inputs, |inputs, layer| layer.forward(inputs))
+            .map(|inputs| self.forward_slice(inputs))
+            .collect()
     }
 
     pub fn parameters(&self) -> Vec<Value> {
@@ -96,6 +534,238 @@ impl MLP {
             .flat_map(|layer| layer.parameters())
             .collect()
     }
+
+    /// Parameters of the unfrozen layers, to hand to an optimizer.
+    pub fn trainable_parameters(&self) -> Vec<Value> {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.trainable_parameters())
+            .collect()
+    }
+
+    /// Magnitude pruning: zeroes every weight with `|w| < threshold` and
+    /// returns how many were pruned. Biases are left alone; see
+    /// [`MLP::prune_with_biases`].
+    pub fn prune(&mut self, threshold: f64) -> usize {
+        self.prune_with_biases(threshold, false)
+    }
+
+    /// Like [`MLP::prune`], also pruning biases when `include_biases` is set.
+    pub fn prune_with_biases(&mut self, threshold: f64, include_biases: bool) -> usize {
+        let mut pruned = 0;
+        for neuron in self
+            .layers
+            .iter_mut()
+            .flat_map(|layer| layer.neurons.iter_mut())
+        {
+            let biases = include_biases.then_some(&mut neuron.bias);
+            for param in neuron.weights.iter_mut().chain(biases) {
+                if param.data().abs() < threshold {
+                    param.set_data(0.0);
+                    pruned += 1;
+                }
+            }
+        }
+        pruned
+    }
+
+    /// The data of every parameter, in [`MLP::parameters`] order.
+    pub fn snapshot(&self) -> Vec<f64> {
+        crate::values_to_f64(&self.parameters())
+    }
+
+    /// Writes a [`MLP::snapshot`] back into the parameters.
+    pub fn restore(&mut self, snapshot: &[f64]) {
+        let params = self.parameters();
+        assert_eq!(
+            params.len(),
+            snapshot.len(),
+            "snapshot has {} values for {} parameters",
+            snapshot.len(),
+            params.len()
+        );
+        for (mut param, &data) in params.into_iter().zip(snapshot.iter()) {
+            param.set_data(data);
+        }
+    }
+
+    /// Statistics of the parameter data, for spotting dead or saturated
+    /// weights.
+    pub fn param_stats(&self) -> ParamStats {
+        ParamStats::from_data(&self.snapshot())
+    }
+
+    /// Statistics of the parameter gradients.
+    pub fn grad_stats(&self) -> ParamStats {
+        let grads: Vec<f64> = self.parameters().iter().map(|p| p.grad_f64()).collect();
+        ParamStats::from_data(&grads)
+    }
+
+    /// Whether any parameter has diverged to NaN or infinity.
+    pub fn has_nan_params(&self) -> bool {
+        self.parameters().iter().any(|p| !p.is_finite())
+    }
+
+    /// Global L2 norm of all parameter gradients.
+    pub fn grad_norm(&self) -> f64 {
+        self.parameters()
+            .iter()
+            .map(|p| p.grad_f64().powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// L2 norm of each layer's parameter gradients.
+    pub fn per_layer_grad_norm(&self) -> Vec<f64> {
+        self.layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .parameters()
+                    .iter()
+                    .map(|p| p.grad_f64().powi(2))
+                    .sum::<f64>()
+                    .sqrt()
+            })
+            .collect()
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.parameters().len()
+    }
+
+    /// A table of each layer's input size, neuron count and parameter count.
+    pub fn summary(&self) -> String {
+        let mut result = format!(
+            "{:<8}{:>8}{:>10}{:>10}\n",
+            "layer", "inputs", "neurons", "params"
+        );
+        for (i, layer) in self.layers.iter().enumerate() {
+            let inputs = layer.neurons.first().map_or(0, |n| n.weights.len());
+            result.push_str(&format!(
+                "{:<8}{:>8}{:>10}{:>10}\n",
+                i,
+                inputs,
+                layer.neurons.len(),
+                layer.parameters().len()
+            ));
+        }
+        result.push_str(&format!("total params: {}", self.num_parameters()));
+        result
+    }
+
+    pub fn apply_to_params(&self, f: impl Fn(&Value)) {
+        for layer in self.layers.iter() {
+            layer.apply_to_params(&f);
+        }
+    }
+}
+
+/// Batch normalization: each feature is normalized by its mean and variance
+/// across the batch, then scaled by `gamma` and shifted by `beta`. Training
+/// mode also folds the batch statistics into running estimates, which eval
+/// mode normalizes with instead.
+pub struct BatchNorm {
+    gamma: Vec<Value>,
+    beta: Vec<Value>,
+    running_mean: Vec<f64>,
+    running_var: Vec<f64>,
+    momentum: f64,
+    epsilon: f64,
+    training: bool,
+}
+
+impl BatchNorm {
+    /// Starts from the identity transform, `gamma = 1` and `beta = 0`, with
+    /// running statistics of zero mean and unit variance.
+    pub fn new(n_features: usize) -> Self {
+        BatchNorm {
+            gamma: Value::ones(n_features),
+            beta: Value::zeros(n_features),
+            running_mean: vec![0.0; n_features],
+            running_var: vec![1.0; n_features],
+            momentum: 0.1,
+            epsilon: 1e-5,
+            training: true,
+        }
+    }
+
+    /// Normalizes `batch`, a list of examples each holding one value per
+    /// feature. In training mode gradients also flow through the batch mean
+    /// and variance.
+    ///
+    /// The running variance is updated with the biased (divide by `n`) batch
+    /// variance, where PyTorch uses the unbiased one; a batch of one example
+    /// therefore contributes a variance of zero. Panics on an empty batch.
+    pub fn forward(&mut self, batch: &[Vec<Value>]) -> Vec<Vec<Value>> {
+        let n_features = self.gamma.len();
+        assert!(!batch.is_empty(), "batch must not be empty");
+        assert!(
+            batch.iter().all(|example| example.len() == n_features),
+            "every example must have {} features",
+            n_features
+        );
+        let mut outputs = vec![Vec::with_capacity(n_features); batch.len()];
+        for j in 0..n_features {
+            let column: Vec<Value> = batch.iter().map(|example| example[j].clone()).collect();
+            let normalized: Vec<Value> = if self.training {
+                let mean = crate::mean(&column);
+                let centered: Vec<Value> = column.iter().map(|x| x - &mean).collect();
+                let squares: Vec<Value> = centered.iter().map(|c| c.powi(2)).collect();
+                let var = crate::mean(&squares);
+                self.running_mean[j] =
+                    (1.0 - self.momentum) * self.running_mean[j] + self.momentum * mean.data();
+                self.running_var[j] =
+                    (1.0 - self.momentum) * self.running_var[j] + self.momentum * var.data();
+                let inv_std = (&var + self.epsilon).sqrt().recip();
+                centered.iter().map(|c| c * &inv_std).collect()
+            } else {
+                let inv_std = 1.0 / (self.running_var[j] + self.epsilon).sqrt();
+                column
+                    .iter()
+                    .map(|x| &(x - self.running_mean[j]) * inv_std)
+                    .collect()
+            };
+            for (output, x_hat) in outputs.iter_mut().zip(normalized.iter()) {
+                output.push(&(x_hat * &self.gamma[j]) + &self.beta[j]);
+            }
+        }
+        outputs
+    }
+
+    pub fn train(&mut self) {
+        self.training = true;
+    }
+
+    pub fn eval(&mut self) {
+        self.training = false;
+    }
+
+    pub fn running_mean(&self) -> &[f64] {
+        &self.running_mean
+    }
+
+    pub fn running_var(&self) -> &[f64] {
+        &self.running_var
+    }
+
+    /// `gamma` followed by `beta`.
+    pub fn parameters(&self) -> Vec<Value> {
+        self.gamma.iter().chain(self.beta.iter()).cloned().collect()
+    }
+}
+
+/// A seeded 2-3-1 tanh network, the fixture shared by the tests below and
+/// by the trainer's.
+#[cfg(test)]
+pub(crate) fn small_mlp(seed: u64) -> MLP {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    MLP::from_sizes_seeded(
+        &[2, 3, 1],
+        &[tanh_fn, tanh_fn],
+        &mut StdRng::seed_from_u64(seed),
+    )
 }
 
 #[test]
@@ -116,7 +786,7 @@ fn test_mlp() {
         error.backward();
         let params: Vec<Value> = mlp.parameters();
         for mut param in params {
-            param.set_data(param.data() - learning_rate * f64::from(param.grad()));
+            param.set_data(param.data() - learning_rate * param.grad_f64());
             param.zero_grad();
         }
     }
@@ -124,3 +794,656 @@ fn test_mlp() {
     let error = (&1.0.into() - &output[0]).pow(2.);
     assert!(error.data() < 0.1);
 }
+
+/// On-disk form of a layer: weights are stored flat, one neuron after another.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SavedLayer {
+    n_inputs: usize,
+    n_neurons: usize,
+    weights: Vec<f64>,
+    biases: Vec<f64>,
+}
+
+#[cfg(feature = "serde")]
+impl MLP {
+    /// Writes the layer dimensions, weights and biases to `path` as JSON.
+    /// Activation functions are not saved.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let layers: Vec<SavedLayer> = self
+            .layers
+            .iter()
+            .map(|layer| SavedLayer {
+                n_inputs: layer.neurons.first().map_or(0, |n| n.weights.len()),
+                n_neurons: layer.neurons.len(),
+                weights: layer
+                    .neurons
+                    .iter()
+                    .flat_map(|n| n.weights.iter().map(|w| w.data()))
+                    .collect(),
+                biases: layer.neurons.iter().map(|n| n.bias.data()).collect(),
+            })
+            .collect();
+        fs::write(path, serde_json::to_string(&layers)?)
+    }
+
+    /// Rebuilds a network saved with [`MLP::save`], applying `funcs[i]` after
+    /// layer `i` since functions cannot be serialized. A file whose sizes
+    /// don't add up, or that doesn't have one layer per function, is
+    /// reported as [`io::ErrorKind::InvalidData`].
+    pub fn load(path: &Path, funcs: &[fn(Value) -> Value]) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let saved_layers: Vec<SavedLayer> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if funcs.len() != saved_layers.len() {
+            return Err(invalid(format!(
+                "{} saved layers but {} activations",
+                saved_layers.len(),
+                funcs.len()
+            )));
+        }
+        let mut layers = Vec::with_capacity(saved_layers.len());
+        for (i, (saved, func)) in saved_layers.into_iter().zip(funcs.iter()).enumerate() {
+            if saved.n_neurons.checked_mul(saved.n_inputs) != Some(saved.weights.len())
+                || saved.n_neurons != saved.biases.len()
+            {
+                return Err(invalid(format!(
+                    "layer {} declares {}x{} weights and {} biases but stores {} and {}",
+                    i,
+                    saved.n_neurons,
+                    saved.n_inputs,
+                    saved.n_neurons,
+                    saved.weights.len(),
+                    saved.biases.len()
+                )));
+            }
+            if let Some(previous) = layers.last().map(|l: &Layer| l.neurons.len()) {
+                if saved.n_inputs != previous {
+                    return Err(invalid(format!(
+                        "layer {} takes {} inputs but the previous layer has {} outputs",
+                        i, saved.n_inputs, previous
+                    )));
+                }
+            }
+            let neurons = (0..saved.n_neurons)
+                .map(|j| {
+                    let weights = &saved.weights[j * saved.n_inputs..(j + 1) * saved.n_inputs];
+                    Neuron::new(Value::vec_from(weights), Value::new(saved.biases[j]))
+                })
+                .collect();
+            layers.push(Layer::new(neurons, *func));
+        }
+        Ok(MLP { layers })
+    }
+}
+
+#[cfg(test)]
+fn weight_variance(neuron: &Neuron) -> f64 {
+    let n = neuron.weights.len() as f64;
+    let mean = neuron.weights.iter().map(|w| w.data()).sum::<f64>() / n;
+    neuron
+        .weights
+        .iter()
+        .map(|w| (w.data() - mean).powi(2))
+        .sum::<f64>()
+        / n
+}
+
+#[test]
+fn test_xavier_init() {
+    let neuron = Neuron::new_xavier(20000, 10000);
+    let target = 2.0 / 30000.0; // limit^2 / 3
+    let variance = weight_variance(&neuron);
+    assert!((variance - target).abs() / target < 0.05);
+    assert!(neuron.bias.approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_he_init() {
+    let neuron = Neuron::new_he(20000);
+    let target = 2.0 / 20000.0;
+    let variance = weight_variance(&neuron);
+    assert!((variance - target).abs() / target < 0.05);
+}
+
+#[test]
+fn test_mlp_init() {
+    let mut mlp = MLP::new();
+    mlp.add_layer_with_init(3, 4, |x: Value| x.relu(), Init::He);
+    mlp.add_layer_with_init(4, 2, |x: Value| x.tanh(), Init::Xavier);
+    assert_eq!(mlp.parameters().len(), 3 * 4 + 4 + 4 * 2 + 2);
+    let limit = (6.0_f64 / 6.0).sqrt();
+    for neuron in mlp.layers[1].neurons.iter() {
+        assert!(neuron.weights.iter().all(|w| w.data().abs() <= limit));
+    }
+}
+
+#[test]
+fn test_from_sizes() {
+    let mlp = MLP::from_sizes(&[3, 4, 4, 1], &[sigmoid_fn, tanh_fn, sigmoid_fn]);
+    let sizes: Vec<(usize, usize)> = mlp
+        .layers
+        .iter()
+        .map(|layer| (layer.neurons[0].weights.len(), layer.neurons.len()))
+        .collect();
+    assert_eq!(sizes, vec![(3, 4), (4, 4), (4, 1)]);
+    let output = mlp.forward(vec![Value::new(1.0), Value::new(2.0), Value::new(2.0)]);
+    assert_eq!(output.len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "one activation per layer")]
+fn test_from_sizes_mismatch() {
+    MLP::from_sizes(&[3, 4, 1], &[tanh_fn]);
+}
+
+#[test]
+#[should_panic(expected = "must match the previous layer")]
+fn test_add_layer_mismatch() {
+    let mut mlp = MLP::new();
+    mlp.add_layer(3, 4, |x: Value| x.tanh());
+    mlp.add_layer(5, 1, |x: Value| x.tanh());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_save_load() {
+    let funcs = [sigmoid_fn, tanh_fn, sigmoid_fn];
+    let mlp = MLP::from_sizes(&[3, 4, 4, 1], &funcs);
+    let path = std::env::temp_dir().join(format!("micrograd_mlp_{}.json", std::process::id()));
+    mlp.save(&path).unwrap();
+    let loaded = MLP::load(&path, &funcs).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let inputs = vec![Value::new(1.0), Value::new(2.0), Value::new(2.0)];
+    let before: Vec<f64> = mlp
+        .forward(inputs.clone())
+        .iter()
+        .map(|v| v.data())
+        .collect();
+    let after: Vec<f64> = loaded.forward(inputs).iter().map(|v| v.data()).collect();
+    assert_eq!(before, after);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_load_invalid() {
+    let path = std::env::temp_dir().join(format!("micrograd_bad_{}.json", std::process::id()));
+    let load = |json: &str, funcs: &[fn(Value) -> Value]| {
+        fs::write(&path, json).unwrap();
+        MLP::load(&path, funcs).map(|_| ()).map_err(|e| e.kind())
+    };
+    let ok = r#"[{"n_inputs":2,"n_neurons":1,"weights":[0.5,-0.5],"biases":[0.1]}]"#;
+    assert_eq!(load(ok, &[tanh_fn]), Ok(()));
+
+    let invalid = Err(io::ErrorKind::InvalidData);
+    // truncated file, reported by serde_json as UnexpectedEof
+    assert!(load(&ok[..ok.len() - 5], &[tanh_fn]).is_err());
+    // weights missing for the declared size
+    let short = r#"[{"n_inputs":2,"n_neurons":2,"weights":[0.5,-0.5],"biases":[0.1,0.2]}]"#;
+    assert_eq!(load(short, &[tanh_fn]), invalid);
+    // a bias too few
+    let biases = r#"[{"n_inputs":2,"n_neurons":1,"weights":[0.5,-0.5],"biases":[]}]"#;
+    assert_eq!(load(biases, &[tanh_fn]), invalid);
+    // second layer doesn't match the first one's outputs
+    let chained = r#"[{"n_inputs":2,"n_neurons":1,"weights":[0.5,-0.5],"biases":[0.1]},
+                      {"n_inputs":3,"n_neurons":1,"weights":[1,1,1],"biases":[0]}]"#;
+    assert_eq!(load(chained, &[tanh_fn, tanh_fn]), invalid);
+    // wrong number of activations
+    assert_eq!(load(ok, &[tanh_fn, tanh_fn]), invalid);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_seeded_mlp() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let build = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut mlp = MLP::from_sizes_seeded(&[3, 4, 4], &[tanh_fn, tanh_fn], &mut rng);
+        mlp.add_layer_with_init_seeded(4, 1, tanh_fn, Init::He, &mut rng);
+        mlp.parameters()
+            .iter()
+            .map(|p| p.data())
+            .collect::<Vec<f64>>()
+    };
+    assert_eq!(build(42), build(42));
+    assert_ne!(build(42), build(43));
+}
+
+#[test]
+fn test_forward_batch() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mlp = MLP::from_sizes_seeded(
+        &[2, 3, 1],
+        &[tanh_fn, tanh_fn],
+        &mut StdRng::seed_from_u64(7),
+    );
+    let batch = vec![
+        vec![Value::new(1.0), Value::new(-1.0)],
+        vec![Value::new(0.5), Value::new(2.0)],
+    ];
+    let weight = mlp.parameters()[0].clone();
+
+    let mut expected = 0.0;
+    for inputs in batch.iter() {
+        let mut output = mlp.forward(inputs.clone())[0].clone();
+        output.backward();
+        expected += weight.grad_f64();
+        for mut param in mlp.parameters() {
+            param.zero_grad();
+        }
+    }
+
+    let outputs = mlp.forward_batch(&batch);
+    assert_eq!(outputs.len(), 2);
+    let mut loss = &outputs[0][0] + &outputs[1][0];
+    loss.backward();
+    assert!(weight.grad_approx_eq(expected, 1e-12));
+}
+
+#[test]
+fn test_wide_layer_forward_slice() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let layer = Layer::new_random_seeded(256, 256, |x| x, &mut rng);
+    let inputs: Vec<Value> = (0..256).map(|i| Value::new(i as f64 / 256.0)).collect();
+    let outputs = layer.forward_slice(&inputs);
+    assert_eq!(outputs.len(), 256);
+    for (neuron, output) in layer.neurons.iter().zip(outputs.iter()) {
+        let expected = neuron.bias.data()
+            + neuron
+                .weights
+                .iter()
+                .zip(inputs.iter())
+                .map(|(w, x)| w.data() * x.data())
+                .sum::<f64>();
+        assert!(output.approx_eq(expected, 1e-9));
+    }
+    assert!(layer.forward(inputs.clone())[0].approx_eq(outputs[0].data(), 1e-12));
+}
+
+#[test]
+fn test_dropout() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let neurons = (0..2000)
+        .map(|_| Neuron::new(vec![Value::new(1.0)], Value::new(0.0)))
+        .collect();
+    let mut mlp = MLP {
+        layers: vec![Layer::new(neurons, identity_fn)],
+    };
+    mlp.set_dropout(0, 0.5);
+    let inputs = vec![Value::new(1.0)];
+
+    mlp.eval();
+    assert!(mlp.forward(inputs.clone()).iter().all(|o| o.data() == 1.0));
+
+    // one draw per unit, in order: dropped below p, otherwise scaled by 2
+    mlp.train();
+    let outputs = mlp.forward_slice_seeded(&inputs, &mut StdRng::seed_from_u64(5));
+    let mut rng = StdRng::seed_from_u64(5);
+    let expected: Vec<f64> = (0..2000)
+        .map(|_| if rng.gen::<f64>() < 0.5 { 0.0 } else { 2.0 })
+        .collect();
+    assert_eq!(crate::values_to_f64(&outputs), expected);
+    let mean = outputs.iter().map(|o| o.data()).sum::<f64>() / outputs.len() as f64;
+    assert!((mean - 1.0).abs() < 0.1);
+
+    let mut total: Value = outputs.iter().sum();
+    total.backward();
+    for (neuron, output) in mlp.layers[0].neurons.iter().zip(outputs.iter()) {
+        assert!(neuron.weights[0].grad_approx_eq(output.data(), 1e-12));
+    }
+}
+
+#[test]
+fn test_dropout_seeded() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let neurons = (0..64)
+        .map(|_| Neuron::new(vec![Value::new(1.0)], Value::new(0.0)))
+        .collect();
+    let mut mlp = MLP {
+        layers: vec![Layer::new(neurons, |x| x)],
+    };
+    mlp.set_dropout(0, 0.5);
+    let inputs = vec![Value::new(1.0)];
+    let mask = |seed| {
+        let outputs = mlp.forward_slice_seeded(&inputs, &mut StdRng::seed_from_u64(seed));
+        crate::values_to_f64(&outputs)
+    };
+    assert_eq!(mask(3), mask(3));
+    assert_ne!(mask(3), mask(4));
+}
+
+#[test]
+fn test_apply_to_params() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let build = || {
+        MLP::from_sizes_seeded(
+            &[3, 4, 1],
+            &[tanh_fn, tanh_fn],
+            &mut StdRng::seed_from_u64(1),
+        )
+    };
+    let inputs = Value::vec_from(&[1.0, 2.0, 2.0]);
+    let lr = 0.1;
+
+    let collected = build();
+    let mut error = (1.0 - &collected.forward(inputs.clone())[0]).pow(2.0);
+    error.backward();
+    for mut param in collected.parameters() {
+        param.set_data(param.data() - lr * param.grad_f64());
+    }
+
+    let visited = build();
+    let mut error = (1.0 - &visited.forward(inputs.clone())[0]).pow(2.0);
+    error.backward();
+    visited.apply_to_params(|p| {
+        let g = p.grad_f64();
+        p.0.borrow_mut().data -= lr * g;
+    });
+
+    let data = |mlp: &MLP| {
+        mlp.parameters()
+            .iter()
+            .map(|p| p.data())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(data(&collected), data(&visited));
+}
+
+#[test]
+fn test_num_parameters() {
+    let mlp = MLP::from_sizes(&[3, 4, 4, 1], &[sigmoid_fn, tanh_fn, sigmoid_fn]);
+    assert_eq!(mlp.num_parameters(), 3 * 4 + 4 + 4 * 4 + 4 + 4 + 1);
+
+    let summary = mlp.summary();
+    let lines: Vec<&str> = summary.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(
+        lines[1].split_whitespace().collect::<Vec<_>>(),
+        ["0", "3", "4", "16"]
+    );
+    assert_eq!(
+        lines[3].split_whitespace().collect::<Vec<_>>(),
+        ["2", "4", "1", "5"]
+    );
+    assert_eq!(lines[4], "total params: 41");
+}
+
+#[test]
+fn test_activation_fns() {
+    let inputs = Value::vec_from(&[-1.0, 0.5]);
+    for (func, expected) in [
+        (relu_fn as fn(Value) -> Value, 1.0),
+        (tanh_fn, 1.0_f64.tanh()),
+        (sigmoid_fn, 1.0 / (1.0 + (-1.0_f64).exp())),
+        (identity_fn, 1.0),
+    ] {
+        // pre-activation: -1 * 1 + 0.5 * 4 = 1
+        let neurons = vec![Neuron::new(Value::vec_from(&[1.0, 4.0]), Value::new(0.0))];
+        let layer = Layer::new(neurons, func);
+        assert!(layer.forward_slice(&inputs)[0].approx_eq(expected, 1e-12));
+        assert_eq!(
+            Layer::new_random(2, 3, func).forward_slice(&inputs).len(),
+            3
+        );
+    }
+}
+
+#[test]
+fn test_freeze() {
+    use crate::optim::SGD;
+
+    let mut mlp = small_mlp(4);
+    mlp.freeze(0);
+    assert_eq!(mlp.trainable_parameters().len(), 4);
+
+    let frozen = crate::values_to_f64(&mlp.layers[0].parameters());
+    let last = crate::values_to_f64(&mlp.layers[1].parameters());
+    let mut sgd = SGD::new(mlp.trainable_parameters(), 0.1);
+    let mut error = (1.0 - &mlp.forward(Value::vec_from(&[0.5, -1.0]))[0]).powi(2);
+    error.backward();
+    assert!(mlp.layers[0]
+        .parameters()
+        .iter()
+        .any(|p| p.grad_f64() != 0.0));
+    sgd.step();
+    assert_eq!(crate::values_to_f64(&mlp.layers[0].parameters()), frozen);
+    assert_ne!(crate::values_to_f64(&mlp.layers[1].parameters()), last);
+
+    mlp.unfreeze(0);
+    assert_eq!(mlp.trainable_parameters().len(), mlp.num_parameters());
+}
+
+#[test]
+fn test_predict() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // two outputs, unlike small_mlp
+    let mut mlp = MLP::from_sizes_seeded(
+        &[3, 4, 2],
+        &[tanh_fn, tanh_fn],
+        &mut StdRng::seed_from_u64(2),
+    );
+    let expected = crate::values_to_f64(&mlp.forward(Value::vec_from(&[1.0, 2.0, 2.0])));
+    assert_eq!(mlp.predict(&[1.0, 2.0, 2.0]), expected);
+
+    // dropout is skipped even while the network is in training mode
+    mlp.set_dropout(0, 0.5);
+    assert_eq!(mlp.predict(&[1.0, 2.0, 2.0]), expected);
+}
+
+#[test]
+fn test_grad_norm() {
+    let mlp = MLP {
+        layers: vec![
+            Layer::new(
+                vec![Neuron::new(Value::vec_from(&[2.0, -1.0]), Value::new(0.5))],
+                identity_fn,
+            ),
+            Layer::new(
+                vec![Neuron::new(Value::vec_from(&[3.0]), Value::new(0.0))],
+                identity_fn,
+            ),
+        ],
+    };
+    assert_eq!(mlp.grad_norm(), 0.0);
+
+    // h = 2*1 - 1*2 + 0.5 = 0.5, y = 3h
+    let mut y = mlp.forward(Value::vec_from(&[1.0, 2.0])).remove(0);
+    y.backward();
+    // first layer grads: 3 * [1, 2, 1]; second layer: [h, 1] = [0.5, 1]
+    let first = (9.0_f64 + 36.0 + 9.0).sqrt();
+    let second = (0.25_f64 + 1.0).sqrt();
+    let per_layer = mlp.per_layer_grad_norm();
+    assert!((per_layer[0] - first).abs() < 1e-12);
+    assert!((per_layer[1] - second).abs() < 1e-12);
+    assert!((mlp.grad_norm() - (first.powi(2) + second.powi(2)).sqrt()).abs() < 1e-12);
+}
+
+#[test]
+fn test_snapshot_restore() {
+    let mut mlp = MLP::from_sizes(&[2, 3, 1], &[tanh_fn, tanh_fn]);
+    let saved = mlp.snapshot();
+    assert_eq!(saved.len(), mlp.num_parameters());
+
+    mlp.apply_to_params(|p| p.update_data(1.0));
+    assert_ne!(mlp.snapshot(), saved);
+    mlp.restore(&saved);
+    assert_eq!(mlp.snapshot(), saved);
+}
+
+#[test]
+fn test_batch_norm() {
+    let batch: Vec<Vec<Value>> = [[1.0, 10.0], [2.0, 20.0], [3.0, 60.0], [6.0, 30.0]]
+        .iter()
+        .map(|example| Value::vec_from(example))
+        .collect();
+    let mut bn = BatchNorm::new(2);
+    let outputs = bn.forward(&batch);
+    for j in 0..2 {
+        let column: Vec<f64> = outputs.iter().map(|o| o[j].data()).collect();
+        let mean = column.iter().sum::<f64>() / 4.0;
+        let var = column.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 4.0;
+        assert!(mean.abs() < 1e-9);
+        assert!((var - 1.0).abs() < 1e-4);
+    }
+    assert!((bn.running_mean()[0] - 0.1 * 3.0).abs() < 1e-12);
+
+    // gamma and beta take part in backward
+    let mut loss: Value = outputs.iter().map(|o| &o[0] * &o[1]).sum();
+    loss.backward();
+    let params = bn.parameters();
+    assert!(params[0].grad_f64() != 0.0);
+    // d/dbeta_0 sum(o_0 * o_1) = sum(o_1)
+    let expected: f64 = outputs.iter().map(|o| o[1].data()).sum();
+    assert!(params[2].grad_approx_eq(expected, 1e-12));
+
+    bn.eval();
+    let out = bn.forward(&[Value::vec_from(&[0.3, 9.0])]);
+    let expected = (0.3 - bn.running_mean()[0]) / (bn.running_var()[0] + 1e-5).sqrt();
+    assert!(out[0][0].approx_eq(expected, 1e-12));
+}
+
+#[test]
+#[should_panic(expected = "batch must not be empty")]
+fn test_batch_norm_empty() {
+    BatchNorm::new(2).forward(&[]);
+}
+
+#[test]
+fn test_prune() {
+    let build = || MLP {
+        layers: vec![Layer::new(
+            vec![
+                Neuron::new(Value::vec_from(&[0.05, -0.5, -0.01]), Value::new(0.02)),
+                Neuron::new(Value::vec_from(&[1.0, 0.09, 0.2]), Value::new(-2.0)),
+            ],
+            identity_fn,
+        )],
+    };
+    let mut mlp = build();
+    assert_eq!(mlp.prune(0.1), 3);
+    assert_eq!(
+        mlp.snapshot(),
+        vec![0.0, -0.5, 0.0, 0.02, 1.0, 0.0, 0.2, -2.0]
+    );
+
+    let mut mlp = build();
+    assert_eq!(mlp.prune_with_biases(0.1, true), 4);
+    assert_eq!(mlp.snapshot()[3], 0.0);
+}
+
+#[test]
+fn test_train_step() {
+    use crate::{loss::mse, optim::SGD};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mlp = MLP::from_sizes_seeded(
+        &[2, 4, 1],
+        &[tanh_fn, identity_fn],
+        &mut StdRng::seed_from_u64(6),
+    );
+    let mut sgd = SGD::new(mlp.parameters(), 0.05);
+    let losses: Vec<f64> = (0..20)
+        .map(|_| mlp.train_step(&[0.5, -1.0], &[0.8], &mut sgd, mse))
+        .collect();
+    assert!(losses.windows(2).all(|w| w[1] < w[0]), "{:?}", losses);
+    assert!(mlp.parameters().iter().all(|p| p.grad_f64() == 0.0));
+}
+
+#[test]
+fn test_param_stats() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mlp = MLP::from_sizes_seeded(
+        &[10, 20, 5],
+        &[tanh_fn, tanh_fn],
+        &mut StdRng::seed_from_u64(8),
+    );
+    let stats = mlp.param_stats();
+    // uniform in -1..1: mean 0, std 1/sqrt(3)
+    assert!(-1.0 <= stats.min && stats.max <= 1.0);
+    assert!(stats.min < stats.mean && stats.mean < stats.max);
+    assert!(stats.mean.abs() < 0.15, "{:?}", stats);
+    assert!(
+        (stats.std - 1.0 / 3.0_f64.sqrt()).abs() < 0.1,
+        "{:?}",
+        stats
+    );
+
+    assert_eq!(mlp.grad_stats(), ParamStats::from_data(&[0.0]));
+    let mut y = mlp.forward(Value::vec_from(&[0.1; 10])).remove(0);
+    y.backward();
+    let grads = mlp.grad_stats();
+    assert!(grads.max > 0.0 || grads.min < 0.0);
+    assert_eq!(MLP::new().param_stats().std, 0.0);
+}
+
+#[test]
+fn test_residual_layer() {
+    let neurons = vec![
+        Neuron::new(Value::vec_from(&[2.0, 0.0]), Value::new(0.0)),
+        Neuron::new(Value::vec_from(&[1.0, -1.0]), Value::new(0.5)),
+    ];
+    let residual = ResidualLayer::new(Layer::new(neurons, identity_fn));
+    let inputs = Value::vec_from(&[1.0, 3.0]);
+    let outputs = residual.forward_slice(&inputs);
+    let inner = residual.layer.forward_slice(&inputs);
+    for i in 0..2 {
+        assert!(outputs[i].approx_eq(inner[i].data() + inputs[i].data(), 1e-12));
+    }
+
+    // dy0/dx0 = 2 + 1 through the layer and the skip
+    let mut y = outputs[0].clone();
+    y.backward();
+    assert!(inputs[0].grad_approx_eq(3.0, 1e-12));
+    assert!(inputs[1].grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+#[should_panic(expected = "needs matching input and output sizes")]
+fn test_residual_layer_mismatch() {
+    ResidualLayer::new(Layer::new_random(3, 2, identity_fn));
+}
+
+#[test]
+fn test_forward_with_activations() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mlp = MLP::from_sizes_seeded(
+        &[3, 5, 4, 2],
+        &[tanh_fn, tanh_fn, tanh_fn],
+        &mut StdRng::seed_from_u64(9),
+    );
+    let (output, activations) = mlp.forward_with_activations(Value::vec_from(&[1.0, -1.0, 0.5]));
+    let sizes: Vec<usize> = activations.iter().map(|a| a.len()).collect();
+    assert_eq!(sizes, vec![5, 4, 2]);
+    assert!(output[0].ptr_eq(&activations[2][0]));
+    assert_eq!(
+        crate::values_to_f64(&output),
+        crate::values_to_f64(&mlp.forward(Value::vec_from(&[1.0, -1.0, 0.5])))
+    );
+
+    // the hidden activations are part of the graph
+    let mut y = output[1].clone();
+    y.backward();
+    assert!(activations[0].iter().any(|h| h.grad_f64() != 0.0));
+}
+
+#[test]
+fn test_has_nan_params() {
+    let mut mlp = small_mlp(7);
+    assert!(!mlp.has_nan_params());
+    let mut params = mlp.snapshot();
+    params[4] = f64::NAN;
+    mlp.restore(&params);
+    assert!(mlp.has_nan_params());
+    params[4] = f64::INFINITY;
+    mlp.restore(&params);
+    assert!(mlp.has_nan_params());
+}
@@ -1,18 +1,12 @@
-use crate::{Operator, Value, ValueInt};
-use std::ops::{Add, Div, Mul, Neg, Sub, SubAssign};
-use std::{cell::RefCell, rc::Rc};
+use crate::{Operator, Value};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 impl<'a> Add for &'a Value {
     type Output = Value;
 
     fn add(self, other: &'a Value) -> Value {
         let data = self.0.borrow().data + other.0.borrow().data;
-        let operator = Operator::Add;
-        Value(Rc::new(RefCell::new(ValueInt {
-            data,
-            operator,
-            prev: vec![self.clone(), other.clone()],
-            grad: 0.0,
-        })))
+        Value::from_op(data, Operator::Add, vec![self.clone(), other.clone()])
     }
 }
 
@@ -21,13 +15,7 @@ impl Add for Value {
 
     fn add(self, other: Value) -> Value {
         let data = self.0.borrow().data + other.0.borrow().data;
-        let operator = Operator::Add;
-        Value(Rc::new(RefCell::new(ValueInt {
-            data,
-            operator,
-            prev: vec![self.clone(), other.clone()],
-            grad: 0.0,
-        })))
+        Value::from_op(data, Operator::Add, vec![self.clone(), other.clone()])
     }
 }
 
@@ -35,7 +23,16 @@ impl Sub for &Value {
     type Output = Value;
 
     fn sub(self, other: Self) -> Value {
-        self + &other.neg()
+        let data = self.0.borrow().data - other.0.borrow().data;
+        Value::from_op(data, Operator::Sub, vec![self.clone(), other.clone()])
+    }
+}
+
+impl Sub for Value {
+    type Output = Value;
+
+    fn sub(self, other: Value) -> Value {
+        &self - &other
     }
 }
 
@@ -51,18 +48,82 @@ impl<'a> SubAssign<&'a Value> for Value {
     }
 }
 
-impl<'a> Neg for &'a Value {
+impl AddAssign for Value {
+    fn add_assign(&mut self, other: Value) {
+        *self = &*self + &other;
+    }
+}
+
+impl<'a> AddAssign<&'a Value> for Value {
+    fn add_assign(&mut self, other: &'a Value) {
+        *self = &*self + other;
+    }
+}
+
+impl MulAssign for Value {
+    fn mul_assign(&mut self, other: Value) {
+        *self = &*self * &other;
+    }
+}
+
+impl<'a> MulAssign<&'a Value> for Value {
+    fn mul_assign(&mut self, other: &'a Value) {
+        *self = &*self * other;
+    }
+}
+
+impl DivAssign for Value {
+    fn div_assign(&mut self, other: Value) {
+        *self = &*self / &other;
+    }
+}
+
+impl<'a> DivAssign<&'a Value> for Value {
+    fn div_assign(&mut self, other: &'a Value) {
+        *self = &*self / other;
+    }
+}
+
+// Unlike the `Value` forms above, the scalar forms are parameter updates:
+// they change `data` in place, shared by every clone of this node, and add
+// nothing to the graph.
+
+impl SubAssign<f64> for Value {
+    fn sub_assign(&mut self, other: f64) {
+        self.0.borrow_mut().data -= other;
+    }
+}
+
+impl AddAssign<f64> for Value {
+    fn add_assign(&mut self, other: f64) {
+        self.0.borrow_mut().data += other;
+    }
+}
+
+impl MulAssign<f64> for Value {
+    fn mul_assign(&mut self, other: f64) {
+        self.0.borrow_mut().data *= other;
+    }
+}
+
+impl Neg for &Value {
     type Output = Value;
 
     fn neg(self) -> Value {
         let data = -self.0.borrow().data;
-        let operator = Operator::Mul;
-        Value(Rc::new(RefCell::new(ValueInt {
+        Value::from_op(
             data,
-            operator,
-            prev: vec![self.clone(), Value::new(-1.0)],
-            grad: 0.0,
-        })))
+            Operator::Mul,
+            vec![self.clone(), Value::no_grad_leaf(-1.0)],
+        )
+    }
+}
+
+impl Neg for Value {
+    type Output = Value;
+
+    fn neg(self) -> Value {
+        -&self
     }
 }
 
@@ -71,13 +132,7 @@ impl<'a> Mul for &'a Value {
 
     fn mul(self, other: &'a Value) -> Value {
         let data = self.0.borrow().data * other.0.borrow().data;
-        let operator = Operator::Mul;
-        Value(Rc::new(RefCell::new(ValueInt {
-            data,
-            operator,
-            prev: vec![self.clone(), other.clone()],
-            grad: 0.0,
-        })))
+        Value::from_op(data, Operator::Mul, vec![self.clone(), other.clone()])
     }
 }
 
@@ -86,13 +141,7 @@ impl Mul for Value {
 
     fn mul(self, other: Value) -> Value {
         let data = self.0.borrow().data * other.0.borrow().data;
-        let operator = Operator::Mul;
-        Value(Rc::new(RefCell::new(ValueInt {
-            data,
-            operator,
-            prev: vec![self.clone(), other.clone()],
-            grad: 0.0,
-        })))
+        Value::from_op(data, Operator::Mul, vec![self.clone(), other.clone()])
     }
 }
 
@@ -100,6 +149,330 @@ impl<'a> Div for &'a Value {
     type Output = Value;
 
     fn div(self, other: &'a Value) -> Value {
-        self * &other.pow(-1.0)
+        let data = self.0.borrow().data / other.0.borrow().data;
+        Value::from_op(data, Operator::Div, vec![self.clone(), other.clone()])
+    }
+}
+
+impl Div for Value {
+    type Output = Value;
+
+    fn div(self, other: Value) -> Value {
+        &self / &other
+    }
+}
+
+impl<'a> Add<&'a Value> for Value {
+    type Output = Value;
+
+    fn add(self, other: &'a Value) -> Value {
+        &self + other
+    }
+}
+
+impl Add<Value> for &Value {
+    type Output = Value;
+
+    fn add(self, other: Value) -> Value {
+        self + &other
+    }
+}
+
+impl<'a> Sub<&'a Value> for Value {
+    type Output = Value;
+
+    fn sub(self, other: &'a Value) -> Value {
+        &self - other
+    }
+}
+
+impl Sub<Value> for &Value {
+    type Output = Value;
+
+    fn sub(self, other: Value) -> Value {
+        self - &other
+    }
+}
+
+impl<'a> Mul<&'a Value> for Value {
+    type Output = Value;
+
+    fn mul(self, other: &'a Value) -> Value {
+        &self * other
+    }
+}
+
+impl Mul<Value> for &Value {
+    type Output = Value;
+
+    fn mul(self, other: Value) -> Value {
+        self * &other
+    }
+}
+
+impl<'a> Div<&'a Value> for Value {
+    type Output = Value;
+
+    fn div(self, other: &'a Value) -> Value {
+        &self / other
+    }
+}
+
+impl Div<Value> for &Value {
+    type Output = Value;
+
+    fn div(self, other: Value) -> Value {
+        self / &other
+    }
+}
+
+impl Add<f64> for &Value {
+    type Output = Value;
+
+    fn add(self, other: f64) -> Value {
+        self + &Value::no_grad_leaf(other)
+    }
+}
+
+impl Add<&Value> for f64 {
+    type Output = Value;
+
+    fn add(self, other: &Value) -> Value {
+        &Value::no_grad_leaf(self) + other
+    }
+}
+
+impl Sub<f64> for &Value {
+    type Output = Value;
+
+    fn sub(self, other: f64) -> Value {
+        self - &Value::no_grad_leaf(other)
+    }
+}
+
+impl Sub<&Value> for f64 {
+    type Output = Value;
+
+    fn sub(self, other: &Value) -> Value {
+        &Value::no_grad_leaf(self) - other
+    }
+}
+
+impl Mul<f64> for &Value {
+    type Output = Value;
+
+    fn mul(self, other: f64) -> Value {
+        self * &Value::no_grad_leaf(other)
+    }
+}
+
+impl Mul<&Value> for f64 {
+    type Output = Value;
+
+    fn mul(self, other: &Value) -> Value {
+        &Value::no_grad_leaf(self) * other
+    }
+}
+
+impl Div<f64> for &Value {
+    type Output = Value;
+
+    fn div(self, other: f64) -> Value {
+        self / &Value::no_grad_leaf(other)
+    }
+}
+
+impl Div<&Value> for f64 {
+    type Output = Value;
+
+    fn div(self, other: &Value) -> Value {
+        &Value::no_grad_leaf(self) / other
+    }
+}
+
+/// Sums as a balanced tree of additions, the same graph `collect::<Value>()`
+/// and [`crate::sum`] build. The empty sum is `0.0`.
+impl Sum for Value {
+    fn sum<I: Iterator<Item = Value>>(iter: I) -> Value {
+        crate::tree_sum(iter.collect())
+    }
+}
+
+impl<'a> Sum<&'a Value> for Value {
+    fn sum<I: Iterator<Item = &'a Value>>(iter: I) -> Value {
+        iter.cloned().sum()
+    }
+}
+
+/// Collects into a single summed value, built as a balanced tree of
+/// additions. The empty collection is `0.0`.
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Value {
+        crate::tree_sum(iter.into_iter().collect())
+    }
+}
+
+#[test]
+fn test_scalar_rhs() {
+    let a = Value::new(6.0);
+    assert!((&a + 2.0).approx_eq(8.0, 1e-12));
+    assert!((&a - 1.0).approx_eq(5.0, 1e-12));
+    assert!((&a * 0.5).approx_eq(3.0, 1e-12));
+    assert!((&a / 3.0).approx_eq(2.0, 1e-12));
+
+    let mut y = &a * 0.5;
+    y.backward();
+    assert!(a.grad_approx_eq(0.5, 1e-12));
+    // the wrapped constant is a no-grad leaf and is left untouched
+    let constant = y.0.borrow().prev[1].clone();
+    assert_eq!(constant.0.borrow().operator, Operator::None);
+    assert!(constant.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_scalar_lhs() {
+    let a = Value::new(6.0);
+    assert!((2.0 + &a).approx_eq(8.0, 1e-12));
+    assert!((1.0 - &a).approx_eq(-5.0, 1e-12));
+    assert!((0.5 * &a).approx_eq(3.0, 1e-12));
+    assert!((3.0 / &a).approx_eq(0.5, 1e-12));
+
+    let mut y = 1.0 - &a;
+    y.backward();
+    assert!(a.grad_approx_eq(-1.0, 1e-12));
+    let constant = y.0.borrow().prev[0].clone();
+    assert_eq!(constant.0.borrow().operator, Operator::None);
+    assert!(constant.grad_approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_sum() {
+    let values = vec![Value::new(1.0), Value::new(2.0), Value::new(3.0)];
+    let mut total: Value = values.iter().sum();
+    assert!(total.approx_eq(6.0, 1e-12));
+    total.backward();
+    for value in values.iter() {
+        assert!(value.grad_approx_eq(1.0, 1e-12));
+    }
+
+    let hand_built = &(&values[0] + &values[1]) + &values[2];
+    let owned: Value = values.clone().into_iter().sum();
+    assert_eq!(owned.get_ops(), hand_built.get_ops());
+
+    // sum() and collect() build the same balanced tree
+    let four = Value::vec_from(&[1.0, 2.0, 3.0, 4.0]);
+    let summed: Value = four.iter().sum();
+    let collected: Value = four.iter().cloned().collect();
+    assert_eq!(summed.get_ops(), "((1+2)+(3+4))");
+    assert_eq!(summed.get_ops(), collected.get_ops());
+
+    let empty: Value = Vec::<Value>::new().into_iter().sum();
+    assert!(empty.approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_assign_ops() {
+    let a = Value::new(2.0);
+    let y = Value::new(3.0);
+    let mut x = a.clone();
+    x += &y;
+    x *= &y;
+    x /= Value::new(5.0);
+    x -= &y;
+    let mut expected = &(&(&(&a + &y) * &y) / &Value::new(5.0)) - &y;
+    assert_eq!(x.get_ops(), expected.get_ops());
+    assert!(x.approx_eq(0.0, 1e-12));
+
+    x.backward();
+    let (a_grad, y_grad) = (a.grad_f64(), y.grad_f64());
+    a.0.borrow_mut().grad = 0.0;
+    y.0.borrow_mut().grad = 0.0;
+    expected.backward();
+    assert!(a.grad_approx_eq(a_grad, 1e-12));
+    assert!(y.grad_approx_eq(y_grad, 1e-12));
+}
+
+#[test]
+fn test_neg_owned() {
+    let a = Value::new(3.0);
+    let mut y = -a.clone();
+    assert!(y.approx_eq(-3.0, 1e-12));
+    y.backward();
+    assert!(a.grad_approx_eq(-1.0, 1e-12));
+
+    assert!((-Value::new(3.0)).approx_eq(-3.0, 1e-12));
+}
+
+#[test]
+fn test_owned_sub_div() {
+    let a = Value::new(6.0);
+    let b = Value::new(2.0);
+    let mut owned = a.clone() - b.clone();
+    let borrowed = &a - &b;
+    assert_eq!(owned.get_ops(), borrowed.get_ops());
+    owned.backward();
+    assert!(a.grad_approx_eq(1.0, 1e-12));
+    assert!(b.grad_approx_eq(-1.0, 1e-12));
+
+    let a = Value::new(6.0);
+    let b = Value::new(2.0);
+    let mut owned = a.clone() / b.clone();
+    let borrowed = &a / &b;
+    assert_eq!(owned.get_ops(), borrowed.get_ops());
+    owned.backward();
+    assert!(a.grad_approx_eq(0.5, 1e-12));
+    assert!(b.grad_approx_eq(-1.5, 1e-12));
+}
+
+#[test]
+fn test_from_iterator() {
+    let values = Value::vec_from(&[1.0, 2.0, 3.0]);
+    let mut total: Value = values.iter().map(|v| v * 2.0).collect();
+    assert!(total.approx_eq(12.0, 1e-12));
+    total.backward();
+    for v in values.iter() {
+        assert!(v.grad_approx_eq(2.0, 1e-12));
+    }
+
+    let empty: Value = Vec::new().into_iter().collect();
+    assert!(empty.approx_eq(0.0, 1e-12));
+}
+
+#[test]
+fn test_scalar_assign() {
+    let mut param = Value::new(2.0);
+    param.set_grad_value(0.25);
+    let alias = param.clone();
+    param -= 0.5;
+    assert!(alias.approx_eq(1.5, 1e-12));
+    assert!(param.grad_approx_eq(0.25, 1e-12));
+    assert!(param.prev().is_empty());
+    param += 1.0;
+    param *= 2.0;
+    assert!(alias.approx_eq(5.0, 1e-12));
+}
+
+#[test]
+fn test_mixed_ownership() {
+    let a = Value::new(6.0);
+    let b = Value::new(3.0);
+    let results = [
+        (a.clone() + &b, 9.0),
+        (&a + b.clone(), 9.0),
+        (a.clone() - &b, 3.0),
+        (&a - b.clone(), 3.0),
+        (a.clone() * &b, 18.0),
+        (&a * b.clone(), 18.0),
+        (a.clone() / &b, 2.0),
+        (&a / b.clone(), 2.0),
+    ];
+    for (result, expected) in results.iter() {
+        assert!(result.approx_eq(*expected, 1e-12));
     }
+    let mut total: Value = results.iter().map(|(r, _)| r).sum();
+    total.backward();
+    // per pair: d/da (a + b, a - b, a * b, a / b) = 1 + 1 + b + 1 / b
+    assert!(a.grad_approx_eq(2.0 * (2.0 + 3.0 + 1.0 / 3.0), 1e-12));
+    // d/db = 1 - 1 + a - a / b^2
+    assert!(b.grad_approx_eq(2.0 * (6.0 - 6.0 / 9.0), 1e-12));
 }
@@ -0,0 +1,264 @@
+use crate::Value;
+
+pub mod scheduler;
+
+/// Common interface over the optimizers, so training code can be generic.
+pub trait Optimizer {
+    fn step(&mut self);
+    fn zero_grad(&mut self);
+    fn lr(&self) -> f64;
+    fn set_lr(&mut self, lr: f64);
+}
+
+pub struct SGD {
+    params: Vec<Value>,
+    lr: f64,
+    momentum: f64,
+    velocity: Vec<f64>,
+}
+
+impl SGD {
+    pub fn new(params: Vec<Value>, lr: f64) -> Self {
+        SGD::with_momentum(params, lr, 0.0)
+    }
+
+    /// Keeps a velocity per parameter, `v = momentum * v - lr * grad`, and
+    /// applies `data += v` on each step.
+    pub fn with_momentum(params: Vec<Value>, lr: f64, momentum: f64) -> Self {
+        let velocity = vec![0.0; params.len()];
+        SGD {
+            params,
+            lr,
+            momentum,
+            velocity,
+        }
+    }
+
+    pub fn step(&mut self) {
+        for (param, v) in self.params.iter().zip(self.velocity.iter_mut()) {
+            let grad = param.grad_f64();
+            *v = self.momentum * *v - self.lr * grad;
+            param.update_data(*v);
+        }
+    }
+
+    pub fn zero_grad(&mut self) {
+        for param in self.params.iter_mut() {
+            param.zero_grad();
+        }
+    }
+}
+
+impl Optimizer for SGD {
+    fn step(&mut self) {
+        SGD::step(self);
+    }
+
+    fn zero_grad(&mut self) {
+        SGD::zero_grad(self);
+    }
+
+    fn lr(&self) -> f64 {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: f64) {
+        self.lr = lr;
+    }
+}
+
+/// Adam with the usual defaults: `beta1 = 0.9`, `beta2 = 0.999`, `epsilon = 1e-8`.
+pub struct Adam {
+    params: Vec<Value>,
+    lr: f64,
+    beta1: f64,
+    beta2: f64,
+    epsilon: f64,
+    m: Vec<f64>,
+    v: Vec<f64>,
+    t: i32,
+}
+
+impl Adam {
+    pub fn new(params: Vec<Value>, lr: f64) -> Self {
+        let m = vec![0.0; params.len()];
+        let v = vec![0.0; params.len()];
+        Adam {
+            params,
+            lr,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            m,
+            v,
+            t: 0,
+        }
+    }
+
+    pub fn step(&mut self) {
+        self.t += 1;
+        let bias1 = 1.0 - self.beta1.powi(self.t);
+        let bias2 = 1.0 - self.beta2.powi(self.t);
+        for ((param, m), v) in self
+            .params
+            .iter()
+            .zip(self.m.iter_mut())
+            .zip(self.v.iter_mut())
+        {
+            let grad = param.grad_f64();
+            *m = self.beta1 * *m + (1.0 - self.beta1) * grad;
+            *v = self.beta2 * *v + (1.0 - self.beta2) * grad * grad;
+            let m_hat = *m / bias1;
+            let v_hat = *v / bias2;
+            param.update_data(-self.lr * m_hat / (v_hat.sqrt() + self.epsilon));
+        }
+    }
+
+    pub fn zero_grad(&mut self) {
+        for param in self.params.iter_mut() {
+            param.zero_grad();
+        }
+    }
+}
+
+impl Optimizer for Adam {
+    fn step(&mut self) {
+        Adam::step(self);
+    }
+
+    fn zero_grad(&mut self) {
+        Adam::zero_grad(self);
+    }
+
+    fn lr(&self) -> f64 {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: f64) {
+        self.lr = lr;
+    }
+}
+
+/// Rescales the gradients of `params` so that their global L2 norm is at
+/// most `max_norm`. Data is left untouched.
+pub fn clip_grad_norm(params: &[Value], max_norm: f64) {
+    let total_norm = params
+        .iter()
+        .map(|param| param.0.borrow().grad.powi(2))
+        .sum::<f64>()
+        .sqrt();
+    if total_norm > max_norm {
+        let scale = max_norm / total_norm;
+        for param in params {
+            param.0.borrow_mut().grad *= scale;
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_network() -> crate::mlp::MLP {
+    let mut mlp = crate::mlp::MLP::new();
+    let sigmo = |x: Value| x.sigmoid();
+    let tanh = |x: Value| x.tanh();
+    mlp.add_layer(3, 4, sigmo);
+    mlp.add_layer(4, 4, tanh);
+    mlp.add_layer(4, 1, sigmo);
+    // fixed weights so that runs are comparable
+    for (i, mut param) in mlp.parameters().into_iter().enumerate() {
+        param.set_data((i as f64 * 0.37).sin() * 0.5);
+    }
+    mlp
+}
+
+#[cfg(test)]
+fn iterations_to_converge(mlp: &crate::mlp::MLP, opt: &mut SGD, target: f64) -> usize {
+    let inputs = vec![Value::new(1.0), Value::new(2.0), Value::new(2.0)];
+    for i in 0..1000 {
+        let output = mlp.forward(inputs.clone());
+        let mut error = (1.0 - &output[0]).pow(2.);
+        if error.data() < target {
+            return i;
+        }
+        error.backward();
+        opt.step();
+        opt.zero_grad();
+    }
+    panic!("did not converge");
+}
+
+#[test]
+fn test_sgd_mlp() {
+    use crate::mlp::MLP;
+
+    let mut mlp = MLP::new();
+    let sigmo = |x: Value| x.sigmoid();
+    let tanh = |x: Value| x.tanh();
+    mlp.add_layer(3, 4, sigmo);
+    mlp.add_layer(4, 4, tanh);
+    mlp.add_layer(4, 1, sigmo);
+    let inputs = vec![Value::new(1.0), Value::new(2.0), Value::new(2.0)];
+    let mut opt = SGD::new(mlp.parameters(), 0.2);
+
+    for _ in 0..30 {
+        let output = mlp.forward(inputs.clone());
+        let mut error = (1.0 - &output[0]).pow(2.);
+        error.backward();
+        opt.step();
+        opt.zero_grad();
+    }
+    let output = mlp.forward(inputs.clone());
+    let error = (1.0 - &output[0]).pow(2.);
+    assert!(error.data() < 0.1);
+}
+
+#[test]
+fn test_sgd_momentum() {
+    let plain_mlp = test_network();
+    let mut plain = SGD::new(plain_mlp.parameters(), 0.2);
+    let plain_iterations = iterations_to_converge(&plain_mlp, &mut plain, 0.01);
+
+    let momentum_mlp = test_network();
+    let mut momentum = SGD::with_momentum(momentum_mlp.parameters(), 0.2, 0.9);
+    let momentum_iterations = iterations_to_converge(&momentum_mlp, &mut momentum, 0.01);
+
+    assert!(momentum_iterations < plain_iterations);
+}
+
+#[test]
+fn test_adam_mlp() {
+    let mlp = test_network();
+    let inputs = vec![Value::new(1.0), Value::new(2.0), Value::new(2.0)];
+    let mut opt = Adam::new(mlp.parameters(), 0.05);
+
+    for _ in 0..50 {
+        let output = mlp.forward(inputs.clone());
+        let mut error = (1.0 - &output[0]).pow(2.);
+        error.backward();
+        opt.step();
+        opt.zero_grad();
+    }
+    let output = mlp.forward(inputs.clone());
+    let error = (1.0 - &output[0]).pow(2.);
+    assert!(error.data() < 0.01);
+}
+
+#[test]
+fn test_clip_grad_norm() {
+    let params = vec![Value::new(1.0), Value::new(2.0)];
+    params[0].0.borrow_mut().grad = 30.0;
+    params[1].0.borrow_mut().grad = 40.0;
+    clip_grad_norm(&params, 1.0);
+
+    let norm = params
+        .iter()
+        .map(|param| param.grad_f64().powi(2))
+        .sum::<f64>()
+        .sqrt();
+    assert!((norm - 1.0).abs() < 1e-12);
+    assert!(params[0].grad_approx_eq(0.6, 1e-12));
+    assert!(params[0].approx_eq(1.0, 1e-12));
+
+    // already within bounds: untouched
+    clip_grad_norm(&params, 5.0);
+    assert!(params[1].grad_approx_eq(0.8, 1e-12));
+}
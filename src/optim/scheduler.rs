@@ -0,0 +1,102 @@
+use super::Optimizer;
+use std::f64::consts::PI;
+
+/// Multiplies the learning rate by `gamma` every `step_size` epochs.
+///
+/// The optimizer's learning rate at the first call to [`StepLR::step`] is
+/// taken as the base rate.
+pub struct StepLR {
+    step_size: usize,
+    gamma: f64,
+    epoch: usize,
+    base_lr: Option<f64>,
+}
+
+impl StepLR {
+    pub fn new(step_size: usize, gamma: f64) -> Self {
+        assert!(step_size > 0, "step_size must be positive");
+        StepLR {
+            step_size,
+            gamma,
+            epoch: 0,
+            base_lr: None,
+        }
+    }
+
+    /// Call once at the end of every epoch.
+    pub fn step(&mut self, optimizer: &mut impl Optimizer) {
+        let base_lr = *self.base_lr.get_or_insert(optimizer.lr());
+        self.epoch += 1;
+        let decays = (self.epoch / self.step_size) as i32;
+        optimizer.set_lr(base_lr * self.gamma.powi(decays));
+    }
+}
+
+/// Anneals the learning rate from its base value down to `eta_min` over
+/// `t_max` epochs along half a cosine period:
+/// `eta_min + (base - eta_min) * (1 + cos(pi * t / t_max)) / 2`.
+///
+/// The optimizer's learning rate at the first call to
+/// [`CosineAnnealingLR::step`] is taken as the base rate.
+pub struct CosineAnnealingLR {
+    t_max: usize,
+    eta_min: f64,
+    epoch: usize,
+    base_lr: Option<f64>,
+}
+
+impl CosineAnnealingLR {
+    pub fn new(t_max: usize, eta_min: f64) -> Self {
+        assert!(t_max > 0, "t_max must be positive");
+        CosineAnnealingLR {
+            t_max,
+            eta_min,
+            epoch: 0,
+            base_lr: None,
+        }
+    }
+
+    /// Call once at the end of every epoch.
+    pub fn step(&mut self, optimizer: &mut impl Optimizer) {
+        let base_lr = *self.base_lr.get_or_insert(optimizer.lr());
+        self.epoch += 1;
+        let t = self.epoch.min(self.t_max) as f64;
+        let cosine = (1.0 + (PI * t / self.t_max as f64).cos()) / 2.0;
+        optimizer.set_lr(self.eta_min + (base_lr - self.eta_min) * cosine);
+    }
+}
+
+#[test]
+fn test_step_lr() {
+    use crate::optim::SGD;
+
+    let mut optimizer = SGD::new(vec![], 1.0);
+    let mut scheduler = StepLR::new(2, 0.5);
+    let mut lrs = vec![];
+    for _ in 0..6 {
+        scheduler.step(&mut optimizer);
+        lrs.push(optimizer.lr());
+    }
+    assert_eq!(lrs, vec![1.0, 0.5, 0.5, 0.25, 0.25, 0.125]);
+}
+
+#[test]
+fn test_cosine_annealing_lr() {
+    use crate::optim::SGD;
+
+    let mut optimizer = SGD::new(vec![], 0.1);
+    let mut scheduler = CosineAnnealingLR::new(4, 0.0);
+    let mut lrs = vec![];
+    for _ in 0..5 {
+        scheduler.step(&mut optimizer);
+        lrs.push(optimizer.lr());
+    }
+    for (t, lr) in lrs.iter().enumerate() {
+        let t = (t + 1).min(4) as f64;
+        let expected = 0.1 * (1.0 + (PI * t / 4.0).cos()) / 2.0;
+        assert!((lr - expected).abs() < 1e-12);
+    }
+    assert!((lrs[1] - 0.05).abs() < 1e-12);
+    assert!(lrs[3].abs() < 1e-12);
+    assert_eq!(lrs[3], lrs[4]);
+}
@@ -0,0 +1,114 @@
+use crate::Value;
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+/// An Elman RNN cell, `h_t = tanh(W_ih x_t + W_hh h_{t-1} + b)`. The same
+/// weight values are used at every time step, so backpropagating through an
+/// unrolled sequence adds up each step's contribution in the shared leaves.
+pub struct RnnCell {
+    w_ih: Vec<Vec<Value>>,
+    w_hh: Vec<Vec<Value>>,
+    bias: Vec<Value>,
+}
+
+impl RnnCell {
+    /// `w_ih` is `n_hidden x n_inputs`, `w_hh` is `n_hidden x n_hidden` and
+    /// `bias` has `n_hidden` entries.
+    pub fn new(w_ih: Vec<Vec<Value>>, w_hh: Vec<Vec<Value>>, bias: Vec<Value>) -> Self {
+        let n_hidden = bias.len();
+        assert_eq!(w_ih.len(), n_hidden, "w_ih needs one row per hidden unit");
+        assert!(
+            w_hh.len() == n_hidden && w_hh.iter().all(|row| row.len() == n_hidden),
+            "w_hh must be {} x {}",
+            n_hidden,
+            n_hidden
+        );
+        RnnCell { w_ih, w_hh, bias }
+    }
+
+    pub fn new_random(n_inputs: usize, n_hidden: usize) -> Self {
+        RnnCell::new_random_seeded(n_inputs, n_hidden, &mut rand::thread_rng())
+    }
+
+    /// Every weight and bias uniform in `-1..1`, drawn from `rng`.
+    pub fn new_random_seeded(n_inputs: usize, n_hidden: usize, rng: &mut impl Rng) -> Self {
+        let between = Uniform::from(-1.0..1.);
+        let mut matrix = |cols: usize| -> Vec<Vec<Value>> {
+            (0..n_hidden)
+                .map(|_| (0..cols).map(|_| Value::new(between.sample(rng))).collect())
+                .collect()
+        };
+        let w_ih = matrix(n_inputs);
+        let w_hh = matrix(n_hidden);
+        let bias = (0..n_hidden)
+            .map(|_| Value::new(between.sample(rng)))
+            .collect();
+        RnnCell { w_ih, w_hh, bias }
+    }
+
+    /// One step: the next hidden state from input `x` and state `h`.
+    pub fn forward(&self, x: &[Value], h: &[Value]) -> Vec<Value> {
+        self.w_ih
+            .iter()
+            .zip(self.w_hh.iter())
+            .zip(self.bias.iter())
+            .map(|((w_ih, w_hh), b)| (&(&Value::dot(w_ih, x) + &Value::dot(w_hh, h)) + b).tanh())
+            .collect()
+    }
+
+    /// Unrolls over `inputs` from a zero initial state, returning the hidden
+    /// state after every step.
+    pub fn forward_sequence(&self, inputs: &[Vec<Value>]) -> Vec<Vec<Value>> {
+        let mut h: Vec<Value> = (0..self.bias.len())
+            .map(|_| Value::no_grad_leaf(0.0))
+            .collect();
+        let mut states = Vec::with_capacity(inputs.len());
+        for x in inputs {
+            h = self.forward(x, &h);
+            states.push(h.clone());
+        }
+        states
+    }
+
+    pub fn parameters(&self) -> Vec<Value> {
+        self.w_ih
+            .iter()
+            .chain(self.w_hh.iter())
+            .flatten()
+            .chain(self.bias.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+#[test]
+fn test_forward_sequence_shares_weights() {
+    let (w, u, b) = (0.5, -0.8, 0.1);
+    let cell = RnnCell::new(
+        vec![vec![Value::new(w)]],
+        vec![vec![Value::new(u)]],
+        vec![Value::new(b)],
+    );
+    let (x1, x2) = (1.0, -2.0);
+    let states = cell.forward_sequence(&[Value::vec_from(&[x1]), Value::vec_from(&[x2])]);
+    assert_eq!(states.len(), 2);
+    let h1 = (w * x1 + b).tanh();
+    let h2 = (w * x2 + u * h1 + b).tanh();
+    assert!(states[0][0].approx_eq(h1, 1e-12));
+    assert!(states[1][0].approx_eq(h2, 1e-12));
+
+    let mut out = states[1][0].clone();
+    out.backward();
+    // dh2/dw: directly through step 2 and through h1 in step 1
+    let expected = (1.0 - h2 * h2) * (x2 + u * (1.0 - h1 * h1) * x1);
+    assert!(cell.w_ih[0][0].grad_approx_eq(expected, 1e-12));
+    assert_eq!(cell.parameters().len(), 3);
+}
+
+#[test]
+fn test_random_shapes() {
+    let cell = RnnCell::new_random(3, 4);
+    assert_eq!(cell.parameters().len(), 4 * 3 + 4 * 4 + 4);
+    let h = cell.forward(&Value::vec_from(&[1.0, 0.0, -1.0]), &Value::zeros(4));
+    assert_eq!(h.len(), 4);
+}
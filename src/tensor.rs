@@ -0,0 +1,142 @@
+use crate::Value;
+use std::ops::{Add, Index, IndexMut, Mul, Sub};
+
+/// A vector of values with elementwise arithmetic. Every operation checks
+/// that both operands have the same length.
+#[derive(Clone, Debug, Default)]
+pub struct Tensor1D(Vec<Value>);
+
+impl Tensor1D {
+    pub fn new(values: Vec<Value>) -> Self {
+        Tensor1D(values)
+    }
+
+    /// A tensor of fresh leaves holding `data`.
+    pub fn from_f64(data: &[f64]) -> Self {
+        Tensor1D(Value::vec_from(data))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Value> {
+        self.0.iter()
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.0
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        self.0
+    }
+
+    pub fn dot(&self, other: &Tensor1D) -> Value {
+        Value::dot(&self.0, &other.0)
+    }
+
+    fn zip_with(&self, other: &Tensor1D, f: impl Fn(&Value, &Value) -> Value) -> Tensor1D {
+        assert_eq!(
+            self.len(),
+            other.len(),
+            "tensor lengths differ: {} vs {}",
+            self.len(),
+            other.len()
+        );
+        Tensor1D(
+            self.0
+                .iter()
+                .zip(other.0.iter())
+                .map(|(a, b)| f(a, b))
+                .collect(),
+        )
+    }
+}
+
+impl From<Vec<Value>> for Tensor1D {
+    fn from(values: Vec<Value>) -> Self {
+        Tensor1D(values)
+    }
+}
+
+impl Index<usize> for Tensor1D {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        &self.0[index]
+    }
+}
+
+impl IndexMut<usize> for Tensor1D {
+    fn index_mut(&mut self, index: usize) -> &mut Value {
+        &mut self.0[index]
+    }
+}
+
+impl<'a> Add for &'a Tensor1D {
+    type Output = Tensor1D;
+
+    fn add(self, other: &'a Tensor1D) -> Tensor1D {
+        self.zip_with(other, |a, b| a + b)
+    }
+}
+
+impl<'a> Sub for &'a Tensor1D {
+    type Output = Tensor1D;
+
+    fn sub(self, other: &'a Tensor1D) -> Tensor1D {
+        self.zip_with(other, |a, b| a - b)
+    }
+}
+
+impl<'a> Mul for &'a Tensor1D {
+    type Output = Tensor1D;
+
+    fn mul(self, other: &'a Tensor1D) -> Tensor1D {
+        self.zip_with(other, |a, b| a * b)
+    }
+}
+
+#[test]
+fn test_elementwise() {
+    let a = Tensor1D::from_f64(&[1.0, 2.0, 3.0]);
+    let b = Tensor1D::from_f64(&[4.0, 5.0, 6.0]);
+    let sum = &a + &b;
+    assert_eq!(crate::values_to_f64(sum.values()), vec![5.0, 7.0, 9.0]);
+    assert_eq!(
+        crate::values_to_f64((&a * &b).values()),
+        vec![4.0, 10.0, 18.0]
+    );
+    assert_eq!(crate::values_to_f64((&b - &a).values()), vec![3.0; 3]);
+
+    let mut total: Value = sum.iter().sum();
+    total.backward();
+    for i in 0..3 {
+        assert!(a[i].grad_approx_eq(1.0, 1e-12));
+        assert!(b[i].grad_approx_eq(1.0, 1e-12));
+    }
+}
+
+#[test]
+fn test_dot() {
+    let a = Tensor1D::from_f64(&[1.0, 2.0, 3.0]);
+    let b = Tensor1D::from_f64(&[4.0, 5.0, 6.0]);
+    let mut d = a.dot(&b);
+    assert!(d.approx_eq(32.0, 1e-12));
+    d.backward();
+    for i in 0..3 {
+        assert!(a[i].grad_approx_eq(b[i].data(), 1e-12));
+        assert!(b[i].grad_approx_eq(a[i].data(), 1e-12));
+    }
+}
+
+#[test]
+#[should_panic(expected = "tensor lengths differ: 1 vs 2")]
+fn test_length_mismatch() {
+    let _ = &Tensor1D::from_f64(&[1.0]) + &Tensor1D::from_f64(&[1.0, 2.0]);
+}
@@ -0,0 +1,223 @@
+use crate::data::Dataset;
+use crate::loss::batch_mean;
+use crate::mlp::MLP;
+use crate::optim::Optimizer;
+use crate::Value;
+use rand::seq::SliceRandom;
+
+/// Runs mini-batch training of an [`MLP`] over an in-memory dataset of
+/// `(input, target)` pairs.
+pub struct Trainer<O: Optimizer> {
+    mlp: MLP,
+    optimizer: O,
+    loss: fn(&[Value], &[Value]) -> Value,
+    data: Vec<(Vec<f64>, Vec<f64>)>,
+}
+
+impl<O: Optimizer> Trainer<O> {
+    /// `optimizer` should have been built over `mlp.parameters()`. Panics if
+    /// `data` is empty.
+    pub fn new(
+        mlp: MLP,
+        optimizer: O,
+        loss: fn(&[Value], &[Value]) -> Value,
+        data: Vec<(Vec<f64>, Vec<f64>)>,
+    ) -> Self {
+        assert!(!data.is_empty(), "training data must not be empty");
+        Trainer {
+            mlp,
+            optimizer,
+            loss,
+            data,
+        }
+    }
+
+    /// Like [`Trainer::new`], copying every example out of `dataset`.
+    pub fn from_dataset(
+        mlp: MLP,
+        optimizer: O,
+        loss: fn(&[Value], &[Value]) -> Value,
+        dataset: &impl Dataset,
+    ) -> Self {
+        let data = (0..dataset.len()).map(|i| dataset.get(i)).collect();
+        Trainer::new(mlp, optimizer, loss, data)
+    }
+
+    pub fn mlp(&self) -> &MLP {
+        &self.mlp
+    }
+
+    /// Trains for `epochs` passes over the data, reshuffled every epoch. Each
+    /// batch's loss is averaged over its examples before a single optimizer
+    /// step. Returns the average per-example loss of every epoch.
+    pub fn fit(&mut self, epochs: usize, batch_size: usize) -> Vec<f64> {
+        assert!(batch_size > 0, "batch_size must be positive");
+        (0..epochs).map(|_| self.train_epoch(batch_size)).collect()
+    }
+
+    /// Like [`Trainer::fit`], but scores `val_data` after every epoch and
+    /// stops once the validation loss has gone `patience` epochs without
+    /// improving. The weights from the best epoch are restored before
+    /// returning that epoch's index.
+    ///
+    /// A NaN or infinite validation loss means training has diverged, so it
+    /// stops there too; if that happens in the first epoch the initial
+    /// weights are restored. Panics if `val_data` is empty.
+    pub fn fit_with_validation(
+        &mut self,
+        epochs: usize,
+        batch_size: usize,
+        val_data: &[(Vec<f64>, Vec<f64>)],
+        patience: usize,
+    ) -> usize {
+        assert!(batch_size > 0, "batch_size must be positive");
+        assert!(!val_data.is_empty(), "val_data must not be empty");
+        let mut best_epoch = 0;
+        let mut best_loss = f64::INFINITY;
+        let mut best_params = self.mlp.snapshot();
+        for epoch in 0..epochs {
+            self.train_epoch(batch_size);
+            let val_loss = self.evaluate(val_data);
+            if !val_loss.is_finite() {
+                break;
+            }
+            if val_loss < best_loss {
+                best_epoch = epoch;
+                best_loss = val_loss;
+                best_params = self.mlp.snapshot();
+            } else if epoch - best_epoch >= patience {
+                break;
+            }
+        }
+        self.mlp.restore(&best_params);
+        best_epoch
+    }
+
+    /// Average per-example loss over `data`, computed without dropout and
+    /// without building a graph.
+    pub fn evaluate(&self, data: &[(Vec<f64>, Vec<f64>)]) -> f64 {
+        let total = crate::with_no_grad(|| {
+            data.iter()
+                .map(|(input, target)| {
+                    let output = Value::vec_from(&self.mlp.predict(input));
+                    (self.loss)(&output, &Value::vec_from(target)).data()
+                })
+                .sum::<f64>()
+        });
+        total / data.len() as f64
+    }
+
+    fn train_epoch(&mut self, batch_size: usize) -> f64 {
+        self.data.shuffle(&mut rand::thread_rng());
+        let mut epoch_loss = 0.0;
+        for batch in self.data.chunks(batch_size) {
+            let losses: Vec<Value> = batch
+                .iter()
+                .map(|(input, target)| {
+                    let output = self.mlp.forward(Value::vec_from(input));
+                    (self.loss)(&output, &Value::vec_from(target))
+                })
+                .collect();
+            let mut loss = batch_mean(&losses);
+            epoch_loss += loss.data() * batch.len() as f64;
+            loss.backward();
+            self.optimizer.step();
+            self.optimizer.zero_grad();
+        }
+        epoch_loss / self.data.len() as f64
+    }
+}
+
+#[test]
+fn test_fit_xor() {
+    use crate::{loss::mse, mlp::tanh_fn, optim::SGD};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mlp = MLP::from_sizes_seeded(
+        &[2, 4, 1],
+        &[tanh_fn, tanh_fn],
+        &mut StdRng::seed_from_u64(3),
+    );
+    let optimizer = SGD::new(mlp.parameters(), 0.1);
+    let data = vec![
+        (vec![0.0, 0.0], vec![-1.0]),
+        (vec![0.0, 1.0], vec![1.0]),
+        (vec![1.0, 0.0], vec![1.0]),
+        (vec![1.0, 1.0], vec![-1.0]),
+    ];
+    let (inputs, targets) = data.into_iter().unzip();
+    let dataset = crate::data::InMemoryDataset::new(inputs, targets);
+    let mut trainer = Trainer::from_dataset(mlp, optimizer, mse, &dataset);
+    let history = trainer.fit(50, 4);
+    assert_eq!(history.len(), 50);
+    assert!(history.windows(2).all(|w| w[1] < w[0]), "{:?}", history);
+}
+
+#[cfg(test)]
+thread_local! {
+    static LOSS_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// `mse` that counts its calls on the current thread, so tests running in
+/// parallel don't see each other's examples.
+#[cfg(test)]
+fn counting_mse(pred: &[Value], target: &[Value]) -> Value {
+    LOSS_CALLS.with(|calls| calls.set(calls.get() + 1));
+    crate::loss::mse(pred, target)
+}
+
+#[test]
+fn test_early_stopping() {
+    use crate::optim::SGD;
+
+    // with a zero learning rate the validation loss never improves after the
+    // first epoch
+    let mlp = crate::mlp::small_mlp(1);
+    let before = mlp.snapshot();
+    let optimizer = SGD::new(mlp.parameters(), 0.0);
+    let data = vec![(vec![0.0, 1.0], vec![1.0]), (vec![1.0, 0.0], vec![-1.0])];
+    let val_data = vec![(vec![1.0, 1.0], vec![0.5])];
+    let mut trainer = Trainer::new(mlp, optimizer, counting_mse, data);
+    let best = trainer.fit_with_validation(100, 1, &val_data, 2);
+    assert_eq!(best, 0);
+    // epoch 0 plus two epochs of patience, each scoring 2 + 1 examples
+    assert_eq!(LOSS_CALLS.with(|calls| calls.get()), 3 * 3);
+    assert_eq!(trainer.mlp().snapshot(), before);
+}
+
+#[test]
+#[should_panic(expected = "training data must not be empty")]
+fn test_trainer_empty_data() {
+    use crate::{loss::mse, optim::SGD};
+
+    let mlp = crate::mlp::small_mlp(4);
+    let optimizer = SGD::new(mlp.parameters(), 0.1);
+    Trainer::new(mlp, optimizer, mse, Vec::new());
+}
+
+#[test]
+#[should_panic(expected = "val_data must not be empty")]
+fn test_fit_with_validation_empty() {
+    use crate::{loss::mse, optim::SGD};
+
+    let mlp = crate::mlp::small_mlp(2);
+    let optimizer = SGD::new(mlp.parameters(), 0.1);
+    let data = vec![(vec![0.0, 1.0], vec![1.0])];
+    Trainer::new(mlp, optimizer, mse, data).fit_with_validation(10, 1, &[], 2);
+}
+
+#[test]
+fn test_fit_with_validation_diverged() {
+    use crate::optim::SGD;
+
+    let mlp = crate::mlp::small_mlp(3);
+    let before = mlp.snapshot();
+    let optimizer = SGD::new(mlp.parameters(), 0.1);
+    let data = vec![(vec![0.0, 1.0], vec![1.0]), (vec![1.0, 0.0], vec![-1.0])];
+    let val_data = vec![(vec![1.0, 1.0], vec![f64::NAN])];
+    let mut trainer = Trainer::new(mlp, optimizer, counting_mse, data);
+    assert_eq!(trainer.fit_with_validation(100, 1, &val_data, 2), 0);
+    // stopped after the first epoch, 2 training examples and 1 validation one
+    assert_eq!(LOSS_CALLS.with(|calls| calls.get()), 3);
+    assert_eq!(trainer.mlp().snapshot(), before);
+}